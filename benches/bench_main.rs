@@ -1,13 +1,17 @@
 use criterion::criterion_main;
 
+mod day1;
 mod day3;
+mod day5;
 mod day6;
 mod day7;
 mod day8;
 mod day9;
 
 criterion_main! {
+    day1::benches,
     day3::benches,
+    day5::benches,
     day6::benches,
     day7::benches,
     day8::benches,
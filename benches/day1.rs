@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, Criterion};
+
+fn day1_bench(c: &mut Criterion) {
+  let mut group = c.benchmark_group("day1");
+  group.measurement_time(Duration::new(10, 0));
+
+  let input = aoc_2024::day1::INPUT.as_bytes();
+
+  group.bench_function("part1", |b| b.iter(|| aoc_2024::day1::part1(input)));
+  group.bench_function("part2", |b| b.iter(|| aoc_2024::day1::part2(input)));
+  group.bench_function("part1_reference", |b| {
+    b.iter(|| aoc_2024::day1::part1_reference(aoc_2024::day1::INPUT))
+  });
+  group.bench_function("part2_reference", |b| {
+    b.iter(|| aoc_2024::day1::part2_reference(aoc_2024::day1::INPUT))
+  });
+
+  group.finish();
+}
+
+criterion_group!(benches, day1_bench);
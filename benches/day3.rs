@@ -12,6 +12,9 @@ fn day3_bench(c: &mut Criterion) {
   group.bench_function("part2", |b| {
     b.iter(|| aoc_2024::day3::parse_and_compute::<true>(aoc_2024::day3::INPUT))
   });
+  group.bench_function("part2_parallel", |b| {
+    b.iter(|| aoc_2024::day3::part2_parallel(aoc_2024::day3::INPUT))
+  });
 
   group.finish();
 }
@@ -6,9 +6,9 @@ fn day6_bench(c: &mut Criterion) {
   let mut group = c.benchmark_group("day6");
   group.measurement_time(Duration::new(10, 0));
 
-  //   group.bench_function("part1", |b| {
-  //     b.iter(|| aoc_2024::day6::parse_and_compute::<false>(aoc_2024::day6::INPUT))
-  //   });
+  group.bench_function("part1", |b| {
+    b.iter(|| aoc_2024::day6::part1(aoc_2024::day6::INPUT_BYTES))
+  });
   group.bench_function("part2", |b| {
     b.iter(|| aoc_2024::day6::part2(aoc_2024::day6::INPUT_BYTES))
   });
@@ -0,0 +1,13 @@
+#![no_main]
+
+use aoc_2024::day3::{parse_and_compute, tokenize, Interpreter};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  let part1 = parse_and_compute::<false>(data);
+  let part2 = parse_and_compute::<true>(data);
+
+  // the fast path has to agree with the general tokenizer + interpreter
+  assert_eq!(part1, Interpreter::part1().run(tokenize(data)).machine.sum);
+  assert_eq!(part2, Interpreter::part2().run(tokenize(data)).machine.sum);
+});
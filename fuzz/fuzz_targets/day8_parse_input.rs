@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fn parse<const EMPTY: i32, const ANTENNAS_ARE_ANTINODES: bool>(data: &[u8]) {
+  let mut positions_by_char = [[(EMPTY, EMPTY); 4]; 123 - 47];
+  let mut antinodes = [[false; 50]; 50];
+  let mut antinode_count = 0usize;
+
+  let res = aoc_2024::day8::parse_input::<EMPTY, ANTENNAS_ARE_ANTINODES>(
+    &mut positions_by_char,
+    data,
+    &mut antinodes,
+    &mut antinode_count,
+  );
+  std::hint::black_box((res, positions_by_char, antinodes, antinode_count));
+}
+
+fuzz_target!(|data: &[u8]| {
+  parse::<5000, false>(data);
+  parse::<{ i32::MIN }, true>(data);
+});
@@ -0,0 +1,29 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+const INPUT_LEN: usize = 20_000;
+
+#[repr(C, align(64))]
+struct Aligned([u8; INPUT_LEN]);
+
+// `part2` isn't fuzzed since it additionally assumes that no more than 5 files ever get moved into
+// the same span, which can't be checked up front.
+fuzz_target!(|data: &[u8]| {
+  let mut buf = Box::new(Aligned([0; INPUT_LEN]));
+
+  // arbitrary bytes, which will almost always be rejected
+  let len = data.len().min(INPUT_LEN);
+  buf.0[..len].copy_from_slice(&data[..len]);
+  let _ = std::hint::black_box(aoc_2024::day9::parse_input_p2(&buf.0[..len]));
+
+  // the same bytes mapped onto something shaped like a real input so that the parser itself gets
+  // exercised as well
+  for (i, out) in buf.0.iter_mut().enumerate() {
+    let b = data.get(i % data.len().max(1)).copied().unwrap_or(0);
+    // files have sizes in 1..=9, free spaces in 0..=9
+    *out = if i % 2 == 0 { '1' as u8 + b % 9 } else { '0' as u8 + b % 10 };
+  }
+  buf.0[INPUT_LEN - 1] = '\n' as u8;
+  let _ = std::hint::black_box(aoc_2024::day9::parse_input_p2(&buf.0));
+});
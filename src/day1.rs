@@ -1,46 +1,279 @@
-use std::collections::HashMap;
+use std::{
+  collections::HashMap,
+  fmt::Display,
+  simd::{num::SimdUint, simd_swizzle, u32x16, u32x8, u8x16},
+};
+
+use crate::runner::answer;
 
 pub const INPUT: &'static str = include_str!("../inputs/day1.txt");
 
-fn parse_input() -> Vec<(usize, usize)> {
-  INPUT
-    .lines()
-    .map(|l| {
-      let mut spl = l.split_ascii_whitespace();
-      let a = spl.next().unwrap().parse::<usize>().unwrap();
-      let b = spl.next().unwrap().parse::<usize>().unwrap();
-      (a, b)
-    })
-    .collect()
+/// Every line of the input looks like `12345   67890\n`
+const LINE_LEN: usize = 14;
+const LINE_COUNT: usize = 1000;
+/// All ids are 5 digits, so they fit in 17 bits and can be sorted with two 9-bit radix passes
+const RADIX_BITS: u32 = 9;
+const RADIX_BUCKETS: usize = 1 << RADIX_BITS;
+
+/// A table of whitespace-separated integers with the same number of columns on every line, like the
+/// two location id lists from the puzzle.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Table {
+  columns: Vec<Vec<i64>>,
 }
 
-pub fn solve() {
-  let input = parse_input();
+impl Table {
+  /// Parses a table from `input`, skipping blank lines.  Returns an error if a value isn't an
+  /// integer or if lines have different numbers of columns.
+  pub fn parse(input: &str) -> Result<Self, String> {
+    let mut columns: Vec<Vec<i64>> = Vec::new();
+
+    for (line_ix, line) in input.lines().enumerate() {
+      if line.trim().is_empty() {
+        continue;
+      }
+
+      let row = line
+        .split_ascii_whitespace()
+        .map(|val| {
+          val
+            .parse::<i64>()
+            .map_err(|err| format!("line {}: invalid value {val:?}: {err}", line_ix + 1))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+      if columns.is_empty() {
+        columns = vec![Vec::new(); row.len()];
+      } else if row.len() != columns.len() {
+        return Err(format!(
+          "line {}: expected {} columns but found {}",
+          line_ix + 1,
+          columns.len(),
+          row.len()
+        ));
+      }
+
+      for (column, val) in columns.iter_mut().zip(row) {
+        column.push(val);
+      }
+    }
+
+    Ok(Table { columns })
+  }
+
+  pub fn column_count(&self) -> usize { self.columns.len() }
+
+  pub fn row_count(&self) -> usize { self.columns.first().map(|col| col.len()).unwrap_or(0) }
+
+  /// Panics if `ix` is out of range
+  pub fn column(&self, ix: usize) -> &[i64] {
+    assert!(
+      ix < self.columns.len(),
+      "column {ix} out of range for table with {} columns",
+      self.columns.len()
+    );
+    &self.columns[ix]
+  }
+
+  fn sorted_column(&self, ix: usize) -> Vec<i64> {
+    let mut col = self.column(ix).to_vec();
+    col.sort_unstable();
+    col
+  }
+
+  /// Sorts columns `a` and `b` independently, pairs them up smallest to largest, and sums the
+  /// distances between each pair (part 1).
+  pub fn sorted_distance(&self, a: usize, b: usize) -> u64 {
+    let a = self.sorted_column(a);
+    let b = self.sorted_column(b);
+    a.iter().zip(&b).map(|(&a, &b)| a.abs_diff(b)).sum()
+  }
+
+  /// Sums each value in column `a` multiplied by the number of times it appears in column `b`
+  /// (part 2).
+  pub fn similarity(&self, a: usize, b: usize) -> i64 {
+    let mut appearance_counts: HashMap<i64, i64> = HashMap::new();
+    for &b in self.column(b) {
+      *appearance_counts.entry(b).or_default() += 1;
+    }
+
+    self
+      .column(a)
+      .iter()
+      .map(|a| a * appearance_counts.get(a).copied().unwrap_or(0))
+      .sum()
+  }
+}
+
+/// Straightforward implementation of part 1 used for checking the optimized one
+pub fn part1_reference(input: &str) -> usize {
+  Table::parse(input).unwrap().sorted_distance(0, 1) as usize
+}
+
+/// Straightforward implementation of part 2 used for checking the optimized one
+pub fn part2_reference(input: &str) -> usize {
+  Table::parse(input).unwrap().similarity(0, 1) as usize
+}
+
+/// Multiplying each digit by these and summing each half of the vector gives the two numbers on a
+/// line.  The spaces between them and the newline at the end (plus the start of the next line)
+/// get multiplied by zero.
+const DIGIT_WEIGHTS: u32x16 = u32x16::from_array([
+  10000, 1000, 100, 10, 1, 0, 0, 0, 10000, 1000, 100, 10, 1, 0, 0, 0,
+]);
+
+#[inline(always)]
+fn parse_line(line: u8x16) -> (u32, u32) {
+  let digits = (line - u8x16::splat('0' as u8)).cast::<u32>() * DIGIT_WEIGHTS;
+  let a: u32x8 = simd_swizzle!(digits, [0, 1, 2, 3, 4, 5, 6, 7]);
+  let b: u32x8 = simd_swizzle!(digits, [8, 9, 10, 11, 12, 13, 14, 15]);
+  (a.reduce_sum(), b.reduce_sum())
+}
+
+fn parse_input_fast(input: &[u8]) -> ([u32; LINE_COUNT], [u32; LINE_COUNT]) {
+  assert_eq!(
+    input.len(),
+    LINE_LEN * LINE_COUNT,
+    "expected {LINE_COUNT} lines of 2 5-digit ids"
+  );
 
-  let mut a = input.iter().map(|(a, _b)| *a).collect::<Vec<_>>();
-  a.sort_unstable();
-  let mut b = input.iter().map(|(_a, b)| *b).collect::<Vec<_>>();
-  b.sort_unstable();
+  let mut a = [0u32; LINE_COUNT];
+  let mut b = [0u32; LINE_COUNT];
 
-  let out = a
-    .iter()
-    .copied()
-    .zip(b.iter().copied())
-    .map(|(a, b)| ((a as isize) - (b as isize)).abs())
-    .sum::<isize>();
+  // every line but the last can be loaded directly since there are at least 2 bytes after it
+  for i in 0..LINE_COUNT - 1 {
+    let line = unsafe { input.get_unchecked(i * LINE_LEN..i * LINE_LEN + 16) };
+    let (a_val, b_val) = parse_line(u8x16::from_slice(line));
+    unsafe {
+      *a.get_unchecked_mut(i) = a_val;
+      *b.get_unchecked_mut(i) = b_val;
+    }
+  }
+
+  let mut last_line = [0u8; 16];
+  last_line[..LINE_LEN].copy_from_slice(&input[(LINE_COUNT - 1) * LINE_LEN..]);
+  let (a_val, b_val) = parse_line(u8x16::from_array(last_line));
+  a[LINE_COUNT - 1] = a_val;
+  b[LINE_COUNT - 1] = b_val;
+
+  (a, b)
+}
 
-  println!("Part 1: {out}");
+fn radix_pass<const SHIFT: u32>(src: &[u32; LINE_COUNT], dst: &mut [u32; LINE_COUNT]) {
+  let bucket = |val: u32| ((val >> SHIFT) as usize) & (RADIX_BUCKETS - 1);
 
-  let mut appearance_counts: HashMap<usize, usize> = HashMap::new();
-  for &b in &b {
-    *appearance_counts.entry(b).or_default() += 1;
+  let mut offsets = [0u16; RADIX_BUCKETS];
+  for &val in src {
+    unsafe { *offsets.get_unchecked_mut(bucket(val)) += 1 };
   }
+  let mut total = 0u16;
+  for offset in &mut offsets {
+    let count = *offset;
+    *offset = total;
+    total += count;
+  }
+
+  for &val in src {
+    unsafe {
+      let offset = offsets.get_unchecked_mut(bucket(val));
+      *dst.get_unchecked_mut(*offset as usize) = val;
+      *offset += 1;
+    }
+  }
+}
+
+/// LSD radix sort for values less than 2^18
+fn radix_sort(vals: &mut [u32; LINE_COUNT]) {
+  let mut scratch = [0u32; LINE_COUNT];
+  radix_pass::<0>(vals, &mut scratch);
+  radix_pass::<RADIX_BITS>(&scratch, vals);
+}
+
+fn parse_and_sort(input: &[u8]) -> ([u32; LINE_COUNT], [u32; LINE_COUNT]) {
+  let (mut a, mut b) = parse_input_fast(input);
+  radix_sort(&mut a);
+  radix_sort(&mut b);
+  (a, b)
+}
+
+pub fn part1(input: &[u8]) -> usize {
+  let (a, b) = parse_and_sort(input);
+  a.iter()
+    .zip(b.iter())
+    .map(|(&a, &b)| a.abs_diff(b) as usize)
+    .sum()
+}
+
+pub fn part2(input: &[u8]) -> usize {
+  let (a, b) = parse_and_sort(input);
 
+  // walk both sorted lists at once.  Whenever they line up on the same id, every copy of it in the
+  // left list scores `id * copies in the right list`.
   let mut sum = 0usize;
-  for &a in &a {
-    let appearances = appearance_counts.get(&a).copied().unwrap_or(0);
-    sum += a * appearances;
+  let (mut i, mut j) = (0, 0);
+  while i < LINE_COUNT && j < LINE_COUNT {
+    let (a_val, b_val) = unsafe { (*a.get_unchecked(i), *b.get_unchecked(j)) };
+    if a_val < b_val {
+      i += 1;
+    } else if a_val > b_val {
+      j += 1;
+    } else {
+      let a_start = i;
+      while i < LINE_COUNT && unsafe { *a.get_unchecked(i) } == a_val {
+        i += 1;
+      }
+      let b_start = j;
+      while j < LINE_COUNT && unsafe { *b.get_unchecked(j) } == b_val {
+        j += 1;
+      }
+      sum += a_val as usize * (i - a_start) * (j - b_start);
+    }
   }
 
-  println!("Part 2: {sum}");
+  sum
+}
+
+pub fn solve() {
+  let out = part1(INPUT.as_bytes());
+
+  answer(1, out);
+
+  let sum = part2(INPUT.as_bytes());
+
+  answer(2, sum);
+}
+
+pub fn run(input: &[u8]) -> impl Display { part2(input) }
+
+#[test]
+fn fast_matches_reference() {
+  // ids are drawn from a small pool so that there are plenty of repeats for part 2
+  let mut state = 0x2545f4914f6cdd1du64;
+  let mut next_id = || {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    10000 + (state % 300) * 299
+  };
+  let input = (0..LINE_COUNT)
+    .map(|_| format!("{}   {}\n", next_id(), next_id()))
+    .collect::<String>();
+
+  assert_eq!(part1(input.as_bytes()), part1_reference(&input));
+  assert_eq!(part2(input.as_bytes()), part2_reference(&input));
+}
+
+#[test]
+fn table_with_signed_values_and_extra_columns() {
+  let table = Table::parse("3 4 -1\n4 3 -1\n\n2 5 7\n1 3 3\n3 9 -2\n3 3 1\n").unwrap();
+  assert_eq!(table.column_count(), 3);
+  assert_eq!(table.row_count(), 6);
+
+  assert_eq!(table.sorted_distance(0, 1), 11);
+  assert_eq!(table.similarity(0, 1), 31);
+  assert_eq!(table.sorted_distance(0, 2), 15);
+  assert_eq!(table.similarity(2, 0), 3 * 3 + 1);
+
+  assert!(Table::parse("1 2\n3\n").is_err());
+  assert!(Table::parse("1 x\n").is_err());
 }
@@ -1,5 +1,8 @@
 pub const INPUT: &'static str = include_str!("../inputs/day10.txt");
 
+#[cfg(feature = "local")]
+use crate::runner::answer;
+
 fn parse_input(input: &str) -> Vec<Vec<usize>> {
   input
     .lines()
@@ -73,7 +76,7 @@ pub fn solve() {
 
   let out = total_score;
 
-  println!("Part 1: {out}");
+  answer(1, out);
 
   let mut out = 0usize;
   for start in starting_coords {
@@ -94,5 +97,5 @@ pub fn solve() {
     }
   }
 
-  println!("Part 2: {out}");
+  answer(2, out);
 }
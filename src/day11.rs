@@ -1,5 +1,8 @@
 use itertools::Either;
 
+#[cfg(feature = "local")]
+use crate::runner::answer;
+
 pub const INPUT: &'static str = include_str!("../inputs/day11.txt");
 
 fn parse_input(input: &str) -> Vec<usize> {
@@ -88,5 +91,5 @@ pub fn solve() {
   }
 
   let out = stones.len();
-  println!("Part 1: {out}");
+  answer(1, out);
 }
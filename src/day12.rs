@@ -1,6 +1,12 @@
+use std::{io, path::Path};
+
 use fxhash::{FxHashMap, FxHashSet};
 use pathfinding::prelude::dfs_reach;
 
+use crate::image::{categorical_color, Image};
+#[cfg(feature = "local")]
+use crate::runner::answer;
+
 #[cfg(feature = "local")]
 pub const INPUT: &'static str = include_str!("../inputs/day12.txt");
 
@@ -265,8 +271,16 @@ fn part2(input: &str) -> usize {
 #[cfg(feature = "local")]
 pub fn solve() {
   let cost = part1(INPUT);
-  println!("Part 1: {cost}");
+  answer(1, cost);
 
   let cost = part2(INPUT);
-  println!("Part 2: {cost}");
+  answer(2, cost);
+}
+
+/// Writes an image of the garden to `path` with each region colored by its plant type.
+pub fn export_image(input: &str, path: impl AsRef<Path>) -> io::Result<()> {
+  let input = parse_input(input);
+  Image::from_grid(&input, |_, _, &plant| categorical_color(plant as usize))
+    .scale(6)
+    .save(path)
 }
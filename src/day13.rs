@@ -1,6 +1,10 @@
 use regex::Regex;
+#[cfg(feature = "z3")]
 use z3::ast::{Ast, Int};
 
+#[cfg(feature = "local")]
+use crate::runner::answer;
+
 #[cfg(feature = "local")]
 pub const INPUT: &'static str = include_str!("../inputs/day13.txt");
 
@@ -45,6 +49,7 @@ fn parse_input(input: &str) -> Vec<Puz> {
 
 /// Returns `Some(cost)`` if there is a solution, and `None` if no combination of moves could solve
 /// the puzzle
+#[cfg(feature = "z3")]
 fn solve_puzzle<const IS_PART_1: bool>(puz: &Puz) -> Option<usize> {
   let z3_conf = z3::Config::new();
   let ctx = z3::Context::new(&z3_conf);
@@ -101,6 +106,89 @@ fn solve_puzzle<const IS_PART_1: bool>(puz: &Puz) -> Option<usize> {
   Some(res as usize)
 }
 
+/// Returns `(g, x, y)` such that `a * x + b * y = g = gcd(a, b)`
+#[cfg(not(feature = "z3"))]
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+  if b == 0 {
+    (a, 1, 0)
+  } else {
+    let (g, x, y) = extended_gcd(b, a % b);
+    (g, y, x - (a / b) * y)
+  }
+}
+
+/// Finds the cheapest non-negative `(a_presses, b_presses)` with
+/// `a_presses * a_move + b_presses * b_move = prize`, with A presses costing 3 and B presses 1.
+#[cfg(not(feature = "z3"))]
+fn cheapest_presses_1d(a_move: i128, b_move: i128, prize: i128) -> Option<(i128, i128)> {
+  match (a_move, b_move) {
+    (0, 0) => return (prize == 0).then_some((0, 0)),
+    (0, _) => return (prize % b_move == 0).then_some((0, prize / b_move)),
+    (_, 0) => return (prize % a_move == 0).then_some((prize / a_move, 0)),
+    _ => (),
+  }
+
+  let (g, x, _) = extended_gcd(a_move, b_move);
+  if prize % g != 0 {
+    return None;
+  }
+
+  // all solutions are `a = a0 + k * a_period, b = b0 - k * b_period`, and the cost changes linearly
+  // with `k`, so the cheapest one is at whichever end of the valid range is cheaper
+  let (a_period, b_period) = (b_move / g, a_move / g);
+  let a0 = (x * (prize / g)).rem_euclid(a_period);
+  let (a_presses, b_presses) = if 3 * a_period <= b_period {
+    // pressing A more is no more expensive, so use as few B presses as possible
+    let b_presses = ((prize - a0 * a_move) / b_move).rem_euclid(b_period);
+    ((prize - b_presses * b_move) / a_move, b_presses)
+  } else {
+    (a0, (prize - a0 * a_move) / b_move)
+  };
+
+  (a_presses >= 0 && b_presses >= 0).then_some((a_presses, b_presses))
+}
+
+/// Returns `Some(cost)`` if there is a solution, and `None` if no combination of moves could solve
+/// the puzzle
+#[cfg(not(feature = "z3"))]
+fn solve_puzzle<const IS_PART_1: bool>(puz: &Puz) -> Option<usize> {
+  let offset = if IS_PART_1 { 0 } else { 10000000000000 };
+  let (a_x, a_y) = (puz.a_move_size_x as i128, puz.a_move_size_y as i128);
+  let (b_x, b_y) = (puz.b_move_size_x as i128, puz.b_move_size_y as i128);
+  let (prize_x, prize_y) = (puz.prize_x as i128 + offset, puz.prize_y as i128 + offset);
+
+  // two equations and two unknowns, so if the buttons move in different directions there's exactly
+  // one solution which can be found with Cramer's rule
+  let det = a_x * b_y - a_y * b_x;
+  let (a_presses, b_presses) = if det != 0 {
+    let a_num = prize_x * b_y - prize_y * b_x;
+    let b_num = a_x * prize_y - a_y * prize_x;
+    if a_num % det != 0 || b_num % det != 0 {
+      return None;
+    }
+    (a_num / det, b_num / det)
+  } else {
+    // the buttons move along the same line, so one axis determines the other and there may be many
+    // solutions to pick the cheapest of
+    let (a_presses, b_presses) = if (a_x, b_x) != (0, 0) {
+      cheapest_presses_1d(a_x, b_x, prize_x)?
+    } else {
+      cheapest_presses_1d(a_y, b_y, prize_y)?
+    };
+    if a_presses * a_x + b_presses * b_x != prize_x || a_presses * a_y + b_presses * b_y != prize_y
+    {
+      return None;
+    }
+    (a_presses, b_presses)
+  };
+
+  if a_presses < 0 || b_presses < 0 {
+    return None;
+  }
+  Some((a_presses * 3 + b_presses) as usize)
+}
+
+#[cfg(feature = "local")]
 pub fn solve() {
   let puzzles = parse_input(INPUT);
 
@@ -111,7 +199,7 @@ pub fn solve() {
     }
   }
 
-  println!("Part 1: {total_cost}");
+  answer(1, total_cost);
 
   let mut total_cost = 0usize;
   for puz in &puzzles {
@@ -120,5 +208,81 @@ pub fn solve() {
     }
   }
 
-  println!("Part 2: {total_cost}");
+  answer(2, total_cost);
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400
+
+Button A: X+26, Y+66
+Button B: X+67, Y+21
+Prize: X=12748, Y=12176
+
+Button A: X+17, Y+86
+Button B: X+84, Y+37
+Prize: X=7870, Y=6450
+
+Button A: X+69, Y+23
+Button B: X+27, Y+71
+Prize: X=18641, Y=10279";
+
+#[test]
+fn example() {
+  let puzzles = parse_input(EXAMPLE);
+  let costs = puzzles.iter().map(solve_puzzle::<true>).collect::<Vec<_>>();
+  assert_eq!(costs, [Some(280), None, Some(200), None]);
+  assert_eq!(costs.iter().flatten().sum::<usize>(), 480);
+
+  let costs = puzzles
+    .iter()
+    .map(solve_puzzle::<false>)
+    .collect::<Vec<_>>();
+  assert_eq!(costs, [None, Some(459236326669), None, Some(416082282239)]);
+}
+
+#[cfg(not(feature = "z3"))]
+#[test]
+fn collinear_buttons() {
+  // B moves further per token, so it's used as much as possible
+  assert_eq!(cheapest_presses_1d(2, 1, 10), Some((0, 10)));
+  // A moves further per token, but has to be balanced out by B to land on the prize
+  assert_eq!(cheapest_presses_1d(5, 1, 12), Some((2, 2)));
+  assert_eq!(cheapest_presses_1d(4, 6, 7), None);
+  assert_eq!(cheapest_presses_1d(4, 6, 2), None);
+  assert_eq!(cheapest_presses_1d(0, 0, 0), Some((0, 0)));
+
+  let mut state = 0x2545f4914f6cdd1du64;
+  let mut rand = |n: usize| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state as usize % n
+  };
+
+  for _ in 0..5000 {
+    let (dx, dy) = (rand(4), rand(4));
+    let (a_scale, b_scale) = (rand(4), rand(4));
+    let puz = Puz {
+      a_move_size_x: dx * a_scale,
+      a_move_size_y: dy * a_scale,
+      b_move_size_x: dx * b_scale,
+      b_move_size_y: dy * b_scale,
+      prize_x: dx * rand(20),
+      prize_y: dy * rand(20),
+    };
+
+    // every press moves at least one unit along some axis unless it doesn't move at all, in which
+    // case pressing it never helps
+    let expected = (0..=60)
+      .flat_map(|a| (0..=60).map(move |b| (a, b)))
+      .filter(|&(a, b)| {
+        a * puz.a_move_size_x + b * puz.b_move_size_x == puz.prize_x
+          && a * puz.a_move_size_y + b * puz.b_move_size_y == puz.prize_y
+      })
+      .map(|(a, b)| a * 3 + b)
+      .min();
+    assert_eq!(solve_puzzle::<true>(&puz), expected, "{puz:?}");
+  }
 }
@@ -1,5 +1,13 @@
+use std::{io, path::Path};
+
 use regex::Regex;
 
+use crate::{
+  image::{Format, FrameSequence, Image},
+  runner::answer,
+  viz::{Frame, Player, Rgb},
+};
+
 pub const INPUT: &'static str = include_str!("../inputs/day14.txt");
 
 #[derive(Debug)]
@@ -23,6 +31,55 @@ fn parse_input(input: &str) -> Vec<Bot> {
     .collect()
 }
 
+fn step(bots: &mut [Bot], width: isize, height: isize) {
+  for bot in bots {
+    let mut next_pos = (bot.pos.0 + bot.vel.0, bot.pos.1 + bot.vel.1);
+    if next_pos.0 >= width {
+      next_pos.0 %= width;
+    }
+    if next_pos.1 >= height {
+      next_pos.1 %= height;
+    }
+    if next_pos.0 < 0 {
+      next_pos.0 = width - -next_pos.0;
+    }
+    if next_pos.1 < 0 {
+      next_pos.1 = height - -next_pos.1;
+    }
+    bot.pos = next_pos;
+  }
+}
+
+// the tree has a frame around it, so there will be a couple of rows and columns with lots of bots
+fn looks_like_tree(bots: &[Bot], width: isize, height: isize) -> bool {
+  let mut counts_by_y = vec![0usize; height as usize];
+  let mut counts_by_x = vec![0usize; width as usize];
+  for bot in bots {
+    counts_by_x[bot.pos.0 as usize] += 1;
+    counts_by_y[bot.pos.1 as usize] += 1;
+  }
+
+  counts_by_x.iter().filter(|&&c| c >= 31).count() >= 2
+    && counts_by_y.iter().filter(|&&c| c >= 30).count() >= 2
+}
+
+fn render(bots: &[Bot], width: isize, height: isize) -> Vec<Vec<char>> {
+  let mut out = vec![vec![' '; width as usize]; height as usize];
+  for bot in bots {
+    out[bot.pos.1 as usize][bot.pos.0 as usize] = 'x';
+  }
+  out
+}
+
+fn to_image(bots: &[Bot], width: isize, height: isize) -> Image {
+  let mut img = Image::new(width as usize, height as usize, Rgb(10, 20, 40)).scale(4);
+  img.overlay(
+    bots.iter().map(|bot| (bot.pos.0 as usize, bot.pos.1 as usize)),
+    Rgb(60, 200, 80),
+  );
+  img
+}
+
 pub fn solve() {
   let mut bots = parse_input(INPUT);
 
@@ -48,22 +105,7 @@ pub fn solve() {
   };
 
   for _ in 0..100 {
-    for bot in &mut bots {
-      let mut next_pos = (bot.pos.0 + bot.vel.0, bot.pos.1 + bot.vel.1);
-      if next_pos.0 >= width {
-        next_pos.0 = next_pos.0 % width;
-      }
-      if next_pos.1 >= height {
-        next_pos.1 = next_pos.1 % height;
-      }
-      if next_pos.0 < 0 {
-        next_pos.0 = width - -next_pos.0;
-      }
-      if next_pos.1 < 0 {
-        next_pos.1 = height - -next_pos.1;
-      }
-      bot.pos = next_pos;
-    }
+    step(&mut bots, width, height);
   }
 
   let mut counts_by_quad = [0, 0, 0, 0, 0];
@@ -74,56 +116,65 @@ pub fn solve() {
 
   let out = counts_by_quad[1] * counts_by_quad[2] * counts_by_quad[3] * counts_by_quad[4];
 
-  println!("Part 1: {out}");
+  answer(1, out);
 
   let mut bots = parse_input(INPUT);
   let mut i = 0usize;
   let out = loop {
     i += 1;
-    for bot in &mut bots {
-      let mut next_pos = (bot.pos.0 + bot.vel.0, bot.pos.1 + bot.vel.1);
-      if next_pos.0 >= width {
-        next_pos.0 = next_pos.0 % width;
-      }
-      if next_pos.1 >= height {
-        next_pos.1 = next_pos.1 % height;
-      }
-      if next_pos.0 < 0 {
-        next_pos.0 = width - -next_pos.0;
-      }
-      if next_pos.1 < 0 {
-        next_pos.1 = height - -next_pos.1;
-      }
-      bot.pos = next_pos;
-    }
+    step(&mut bots, width, height);
 
-    let mut counts_by_y = vec![0usize; height as usize];
-    let mut counts_by_x = vec![0usize; width as usize];
-    for bot in &bots {
-      counts_by_x[bot.pos.0 as usize] += 1;
-      counts_by_y[bot.pos.1 as usize] += 1;
+    if looks_like_tree(&bots, width, height) {
+      break i;
     }
+  };
+
+  answer(2, out);
+}
 
-    if counts_by_x.iter().filter(|&&c| c >= 31).count() < 2
-      || counts_by_y.iter().filter(|&&c| c >= 30).count() < 2
-    {
-      continue;
+/// Plays back the bots' movement in the terminal, pausing on every frame that looks like it might
+/// contain the tree.
+pub fn visualize() {
+  let (width, height) = (101, 103);
+  let mut bots = parse_input(INPUT);
+
+  let initial = Frame::new(render(&bots, width, height), "t = 0");
+  let frames = (1..).map(move |t| {
+    step(&mut bots, width, height);
+    let frame = Frame::new(render(&bots, width, height), format!("t = {t}"));
+    if looks_like_tree(&bots, width, height) {
+      frame.pause()
+    } else {
+      frame
     }
+  });
 
-    let mut out = vec![vec![' '; width as usize]; height as usize];
-    for bot in &bots {
-      out[bot.pos.1 as usize][bot.pos.0 as usize] = 'x';
+  Player::new()
+    .fps(30.)
+    .color('x', Rgb(60, 200, 80))
+    .play(std::iter::once(initial).chain(frames));
+}
+
+/// Writes an image of the first frame that looks like it contains the tree to `path`.
+pub fn export_tree_image(path: impl AsRef<Path>) -> io::Result<()> {
+  let (width, height) = (101, 103);
+  let mut bots = parse_input(INPUT);
+  loop {
+    step(&mut bots, width, height);
+    if looks_like_tree(&bots, width, height) {
+      return to_image(&bots, width, height).save(path);
     }
-    // println!("{}", i + 1);
-    // for row in out {
-    //   let s = row.into_iter().collect::<String>();
-    //   println!("{s}");
-    // }
-
-    // println!("\n\n");
-    // std::thread::sleep(Duration::from_millis(200));
-    break i;
-  };
+  }
+}
 
-  println!("Part 2: {out}");
+/// Writes images of the first `frame_count` seconds of the bots' movement to `dir`.
+pub fn export_frames(dir: impl AsRef<Path>, frame_count: usize) -> io::Result<()> {
+  let (width, height) = (101, 103);
+  let mut bots = parse_input(INPUT);
+  let mut frames = FrameSequence::new(dir.as_ref(), Format::Png)?;
+  for _ in 0..frame_count {
+    frames.push(&to_image(&bots, width, height))?;
+    step(&mut bots, width, height);
+  }
+  Ok(())
 }
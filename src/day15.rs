@@ -1,3 +1,8 @@
+use crate::{
+  runner::{self, answer},
+  viz::{Frame, Player, Rgb},
+};
+
 pub const INPUT: &'static str = include_str!("../inputs/day15.txt");
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -89,19 +94,26 @@ fn parse_input(input: &str) -> (Vec<Vec<Slot>>, Vec<Move>, (usize, usize)) {
   (grid, moves, bot_pos)
 }
 
-fn print_grid(grid: &[Vec<Slot>], bot_pos: (usize, usize)) {
-  // std::thread::sleep(Duration::from_millis(10));
-  // print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
-
-  for (y, l) in grid.iter().enumerate() {
-    let mut l = l.iter().map(|s| s.to_c()).collect::<String>();
-    if y == bot_pos.1 {
-      l = l
-        .chars()
+fn render(grid: &[Vec<Slot>], bot_pos: (usize, usize)) -> Vec<Vec<char>> {
+  grid
+    .iter()
+    .enumerate()
+    .map(|(y, l)| {
+      l.iter()
         .enumerate()
-        .map(|(c_ix, c)| if c_ix == bot_pos.0 { '@' } else { c })
-        .collect();
-    }
+        .map(|(x, s)| if (x, y) == bot_pos { '@' } else { s.to_c() })
+        .collect()
+    })
+    .collect()
+}
+
+fn print_grid(grid: &[Vec<Slot>], bot_pos: (usize, usize)) {
+  if runner::quiet() {
+    return;
+  }
+
+  for l in render(grid, bot_pos) {
+    let l = l.into_iter().collect::<String>();
     println!("{l}");
   }
   println!("\n");
@@ -152,201 +164,203 @@ fn parse_input_p2(input: &str) -> (Vec<Vec<Slot>>, Vec<Move>, (usize, usize)) {
   (grid, moves, bot_pos)
 }
 
-pub fn solve() {
-  let (mut grid, moves, mut bot_pos) = parse_input(INPUT);
-
-  'outer: for m in moves {
-    let start_target = m.get_target(bot_pos);
-    if grid[start_target.1][start_target.0] == Slot::Empty {
-      bot_pos = start_target;
-      continue;
-    } else if grid[start_target.1][start_target.0] == Slot::Wall {
-      continue;
-    }
+fn step_p1(grid: &mut [Vec<Slot>], bot_pos: (usize, usize), m: Move) -> (usize, usize) {
+  let start_target = m.get_target(bot_pos);
+  if grid[start_target.1][start_target.0] == Slot::Empty {
+    return start_target;
+  } else if grid[start_target.1][start_target.0] == Slot::Wall {
+    return bot_pos;
+  }
 
-    let mut cur_target = start_target;
-    let mut box_tiles = Vec::new();
-    while grid[cur_target.1][cur_target.0] == Slot::Box {
-      box_tiles.push(cur_target);
-      cur_target = m.get_target(cur_target);
-      if grid[cur_target.1][cur_target.0] == Slot::Wall {
-        continue 'outer;
-      }
+  let mut cur_target = start_target;
+  let mut box_tiles = Vec::new();
+  while grid[cur_target.1][cur_target.0] == Slot::Box {
+    box_tiles.push(cur_target);
+    cur_target = m.get_target(cur_target);
+    if grid[cur_target.1][cur_target.0] == Slot::Wall {
+      return bot_pos;
     }
+  }
 
-    assert_eq!(grid[cur_target.1][cur_target.0], Slot::Empty);
+  assert_eq!(grid[cur_target.1][cur_target.0], Slot::Empty);
 
-    grid[cur_target.1][cur_target.0] = Slot::Box;
-    for &coord in &box_tiles[1..] {
-      grid[coord.1][coord.0] = Slot::Box;
-    }
-    grid[box_tiles[0].1][box_tiles[0].0] = Slot::Empty;
-    bot_pos = start_target;
+  grid[cur_target.1][cur_target.0] = Slot::Box;
+  for &coord in &box_tiles[1..] {
+    grid[coord.1][coord.0] = Slot::Box;
   }
+  grid[box_tiles[0].1][box_tiles[0].0] = Slot::Empty;
+  start_target
+}
 
-  print_grid(&grid, bot_pos);
-
-  let mut out = 0usize;
+fn step_p2(grid: &mut [Vec<Slot>], bot_pos: (usize, usize), m: Move) -> (usize, usize) {
   for y in 0..grid.len() {
     for x in 0..grid[0].len() {
-      if grid[y][x] == Slot::Box {
-        out += gps_coord((x, y));
+      if grid[y][x] == Slot::BoxStart {
+        assert_eq!(grid[y][x + 1], Slot::BoxEnd);
+        assert_ne!((x, y), bot_pos);
+      } else if grid[y][x] == Slot::BoxEnd {
+        assert_eq!(grid[y][x - 1], Slot::BoxStart);
+        assert_ne!((x, y), bot_pos);
       }
     }
   }
 
-  println!("Part 1: {out}");
-
-  let (mut grid, moves, mut bot_pos) = parse_input_p2(INPUT);
+  let start_target = m.get_target(bot_pos);
+  if grid[start_target.1][start_target.0] == Slot::Empty {
+    return start_target;
+  } else if grid[start_target.1][start_target.0] == Slot::Wall {
+    return bot_pos;
+  }
 
-  'outer: for m in moves {
-    // print_grid(&grid, bot_pos);
-
-    for y in 0..grid.len() {
-      for x in 0..grid[0].len() {
-        if grid[y][x] == Slot::BoxStart {
-          assert_eq!(grid[y][x + 1], Slot::BoxEnd);
-          assert_ne!((x, y), bot_pos);
-        } else if grid[y][x] == Slot::BoxEnd {
-          assert_eq!(grid[y][x - 1], Slot::BoxStart);
-          assert_ne!((x, y), bot_pos);
-        }
+  if m == Move::L || m == Move::R {
+    let mut cur_target = start_target;
+    let mut box_count = 0usize;
+    while grid[cur_target.1][cur_target.0] == Slot::BoxStart
+      || grid[cur_target.1][cur_target.0] == Slot::BoxEnd
+    {
+      box_count += 1;
+      cur_target = m.get_target(cur_target);
+      if grid[cur_target.1][cur_target.0] == Slot::Wall {
+        return bot_pos;
       }
     }
 
-    let start_target = m.get_target(bot_pos);
-    if grid[start_target.1][start_target.0] == Slot::Empty {
-      bot_pos = start_target;
-      continue;
-    } else if grid[start_target.1][start_target.0] == Slot::Wall {
-      continue;
-    }
-
-    if m == Move::L || m == Move::R {
-      let mut cur_target = start_target;
-      let mut box_count = 0usize;
-      while grid[cur_target.1][cur_target.0] == Slot::BoxStart
-        || grid[cur_target.1][cur_target.0] == Slot::BoxEnd
-      {
-        box_count += 1;
-        cur_target = m.get_target(cur_target);
-        if grid[cur_target.1][cur_target.0] == Slot::Wall {
-          continue 'outer;
-        }
-      }
+    assert_eq!(grid[cur_target.1][cur_target.0], Slot::Empty);
 
-      assert_eq!(grid[cur_target.1][cur_target.0], Slot::Empty);
-
-      if m == Move::L {
-        let from_slice =
-          grid[start_target.1][start_target.0 - box_count + 1..start_target.0 + 1].to_vec();
-        assert_eq!(from_slice[0], Slot::BoxStart);
-        grid[start_target.1][start_target.0 - box_count..start_target.0]
-          .copy_from_slice(from_slice.as_slice());
-      } else {
-        let from_slice = grid[start_target.1][start_target.0..start_target.0 + box_count].to_vec();
-        assert_eq!(from_slice[0], Slot::BoxStart);
-        grid[start_target.1][start_target.0 + 1..start_target.0 + 1 + box_count]
-          .copy_from_slice(from_slice.as_slice());
-      };
-
-      grid[start_target.1][start_target.0] = Slot::Empty;
-      bot_pos = start_target;
-
-      continue 'outer;
-    }
+    if m == Move::L {
+      let from_slice =
+        grid[start_target.1][start_target.0 - box_count + 1..start_target.0 + 1].to_vec();
+      assert_eq!(from_slice[0], Slot::BoxStart);
+      grid[start_target.1][start_target.0 - box_count..start_target.0]
+        .copy_from_slice(from_slice.as_slice());
+    } else {
+      let from_slice = grid[start_target.1][start_target.0..start_target.0 + box_count].to_vec();
+      assert_eq!(from_slice[0], Slot::BoxStart);
+      grid[start_target.1][start_target.0 + 1..start_target.0 + 1 + box_count]
+        .copy_from_slice(from_slice.as_slice());
+    };
+
+    grid[start_target.1][start_target.0] = Slot::Empty;
+    return start_target;
+  }
 
-    let mut frontier_box_coords = Vec::new();
-    if grid[start_target.1][start_target.0] == Slot::BoxStart
-      || grid[start_target.1][start_target.0] == Slot::BoxEnd
-    {
-      frontier_box_coords.push(if grid[start_target.1][start_target.0] == Slot::BoxStart {
-        (start_target, (start_target.0 + 1, start_target.1))
-      } else {
-        ((start_target.0 - 1, start_target.1), start_target)
-      });
-    }
+  let mut frontier_box_coords = Vec::new();
+  if grid[start_target.1][start_target.0] == Slot::BoxStart
+    || grid[start_target.1][start_target.0] == Slot::BoxEnd
+  {
+    frontier_box_coords.push(if grid[start_target.1][start_target.0] == Slot::BoxStart {
+      (start_target, (start_target.0 + 1, start_target.1))
+    } else {
+      ((start_target.0 - 1, start_target.1), start_target)
+    });
+  }
 
-    let mut valid_box_coords = Vec::new();
-    // let mut invalid_box_coords = Vec::new();
-    // let mut pushers_by_pushee: FxHashMap<(usize, usize), (usize, usize)> = FxHashMap::default();
-
-    let mut next_cur_box_top_coords = Vec::new();
-    let next_y = |y: usize| if m == Move::D { y + 1 } else { y - 1 };
-    while !frontier_box_coords.is_empty() {
-      for bt_coords in frontier_box_coords {
-        for bt_coord in [bt_coords.0, bt_coords.1] {
-          assert!(
-            grid[bt_coord.1][bt_coord.0] == Slot::BoxStart
-              || grid[bt_coord.1][bt_coord.0] == Slot::BoxEnd
-          );
-
-          let next = (bt_coord.0, next_y(bt_coord.1));
-          if grid[next.1][next.0] == Slot::Wall {
-            continue 'outer;
-          } else if grid[next.1][next.0] == Slot::Empty {
-            continue;
-          } else if grid[next.1][next.0] == Slot::BoxStart || grid[next.1][next.0] == Slot::BoxEnd {
-            if grid[next.1][next.0] == Slot::BoxStart {
-              next_cur_box_top_coords.push((next, (next.0 + 1, next.1)));
-            } else {
-              next_cur_box_top_coords.push((next, (next.0 - 1, next.1)));
-            }
+  let mut valid_box_coords = Vec::new();
+  // let mut invalid_box_coords = Vec::new();
+  // let mut pushers_by_pushee: FxHashMap<(usize, usize), (usize, usize)> = FxHashMap::default();
+
+  let mut next_cur_box_top_coords = Vec::new();
+  let next_y = |y: usize| if m == Move::D { y + 1 } else { y - 1 };
+  while !frontier_box_coords.is_empty() {
+    for bt_coords in frontier_box_coords {
+      for bt_coord in [bt_coords.0, bt_coords.1] {
+        assert!(
+          grid[bt_coord.1][bt_coord.0] == Slot::BoxStart
+            || grid[bt_coord.1][bt_coord.0] == Slot::BoxEnd
+        );
+
+        let next = (bt_coord.0, next_y(bt_coord.1));
+        if grid[next.1][next.0] == Slot::Wall {
+          return bot_pos;
+        } else if grid[next.1][next.0] == Slot::Empty {
+          continue;
+        } else if grid[next.1][next.0] == Slot::BoxStart || grid[next.1][next.0] == Slot::BoxEnd {
+          if grid[next.1][next.0] == Slot::BoxStart {
+            next_cur_box_top_coords.push((next, (next.0 + 1, next.1)));
           } else {
-            unreachable!()
+            next_cur_box_top_coords.push((next, (next.0 - 1, next.1)));
           }
+        } else {
+          unreachable!()
         }
-
-        valid_box_coords.push(bt_coords.0);
-        valid_box_coords.push(bt_coords.1);
       }
 
-      frontier_box_coords = next_cur_box_top_coords.clone();
-      next_cur_box_top_coords = Vec::new();
+      valid_box_coords.push(bt_coords.0);
+      valid_box_coords.push(bt_coords.1);
     }
 
-    assert!(!valid_box_coords.is_empty());
-
-    let mut min_y = usize::MAX;
-    let mut max_y = usize::MIN;
-    let vals = valid_box_coords
-      .iter()
-      .map(|c| {
-        min_y = min_y.min(c.1);
-        max_y = max_y.max(c.1);
-        grid[c.1][c.0]
-      })
-      .collect::<Vec<_>>();
-
-    if m == Move::D {
-      for src_y in (min_y..=max_y).rev() {
-        let to_move = valid_box_coords
-          .iter()
-          .enumerate()
-          .filter(|(_i, c)| c.1 == src_y)
-          .map(|(i, c)| (c, vals[i]));
-        for (coord, val) in to_move {
-          grid[coord.1 + 1][coord.0] = val;
-          grid[coord.1][coord.0] = Slot::Empty;
-        }
+    frontier_box_coords = next_cur_box_top_coords.clone();
+    next_cur_box_top_coords = Vec::new();
+  }
+
+  assert!(!valid_box_coords.is_empty());
+
+  let mut min_y = usize::MAX;
+  let mut max_y = usize::MIN;
+  let vals = valid_box_coords
+    .iter()
+    .map(|c| {
+      min_y = min_y.min(c.1);
+      max_y = max_y.max(c.1);
+      grid[c.1][c.0]
+    })
+    .collect::<Vec<_>>();
+
+  if m == Move::D {
+    for src_y in (min_y..=max_y).rev() {
+      let to_move = valid_box_coords
+        .iter()
+        .enumerate()
+        .filter(|(_i, c)| c.1 == src_y)
+        .map(|(i, c)| (c, vals[i]));
+      for (coord, val) in to_move {
+        grid[coord.1 + 1][coord.0] = val;
+        grid[coord.1][coord.0] = Slot::Empty;
       }
-    } else if m == Move::U {
-      for src_y in min_y..=max_y {
-        let to_move = valid_box_coords
-          .iter()
-          .enumerate()
-          .filter(|(_i, c)| c.1 == src_y)
-          .map(|(i, c)| (c, vals[i]));
-        for (coord, val) in to_move {
-          grid[coord.1 - 1][coord.0] = val;
-          grid[coord.1][coord.0] = Slot::Empty;
-        }
+    }
+  } else if m == Move::U {
+    for src_y in min_y..=max_y {
+      let to_move = valid_box_coords
+        .iter()
+        .enumerate()
+        .filter(|(_i, c)| c.1 == src_y)
+        .map(|(i, c)| (c, vals[i]));
+      for (coord, val) in to_move {
+        grid[coord.1 - 1][coord.0] = val;
+        grid[coord.1][coord.0] = Slot::Empty;
+      }
+    }
+  } else {
+    unreachable!()
+  }
+
+  start_target
+}
+
+pub fn solve() {
+  let (mut grid, moves, mut bot_pos) = parse_input(INPUT);
+
+  for m in moves {
+    bot_pos = step_p1(&mut grid, bot_pos, m);
+  }
+
+  print_grid(&grid, bot_pos);
+
+  let mut out = 0usize;
+  for (y, row) in grid.iter().enumerate() {
+    for (x, slot) in row.iter().enumerate() {
+      if *slot == Slot::Box {
+        out += gps_coord((x, y));
       }
-    } else {
-      unreachable!()
     }
+  }
 
-    bot_pos = start_target;
+  answer(1, out);
+
+  let (mut grid, moves, mut bot_pos) = parse_input_p2(INPUT);
+
+  for m in moves {
+    bot_pos = step_p2(&mut grid, bot_pos, m);
   }
 
   print_grid(&grid, bot_pos);
@@ -360,5 +374,32 @@ pub fn solve() {
     }
   }
 
-  println!("Part 2: {out}");
+  answer(2, out);
+}
+
+/// Plays back the robot's moves in the terminal, using the wide warehouse from part 2 if `part2`
+/// is set.
+pub fn visualize(part2: bool) {
+  let (mut grid, moves, mut bot_pos) =
+    if part2 { parse_input_p2(INPUT) } else { parse_input(INPUT) };
+  let step = if part2 { step_p2 } else { step_p1 };
+
+  let move_count = moves.len();
+  let initial = Frame::new(render(&grid, bot_pos), format!("move 0/{move_count}"));
+  let frames = moves.into_iter().enumerate().map(move |(i, m)| {
+    bot_pos = step(&mut grid, bot_pos, m);
+    Frame::new(
+      render(&grid, bot_pos),
+      format!("move {}/{move_count}: {m:?}", i + 1),
+    )
+  });
+
+  Player::new()
+    .fps(60.)
+    .color('#', Rgb(110, 110, 110))
+    .color('O', Rgb(214, 160, 60))
+    .color('[', Rgb(214, 160, 60))
+    .color(']', Rgb(214, 160, 60))
+    .color('@', Rgb(80, 220, 100))
+    .play(std::iter::once(initial).chain(frames));
 }
@@ -1,9 +1,14 @@
-use std::sync::{Arc, Mutex};
+use std::{io, path::Path, sync::Mutex};
 
 use fxhash::FxHashSet;
 use pathfinding::directed::astar::astar;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+use crate::{
+  image::{Image, Rgb},
+  runner::answer,
+};
+
 pub const INPUT: &str = include_str!("../inputs/day16.txt");
 
 fn parse_input(input: &str) -> Vec<Vec<char>> {
@@ -18,9 +23,7 @@ enum Dir {
   R,
 }
 
-pub fn solve() {
-  let grid = parse_input(INPUT);
-
+fn find_start_end(grid: &[Vec<char>]) -> ((usize, usize), (usize, usize)) {
   let mut start = (0, 0);
   let mut end = (0, 0);
   for y in 0..grid.len() {
@@ -32,50 +35,56 @@ pub fn solve() {
       }
     }
   }
+  (start, end)
+}
 
-  fn successors<'a>(
-    grid: &'a [Vec<char>],
-    &(dir, (x, y)): &(Dir, (usize, usize)),
-  ) -> impl Iterator<Item = ((Dir, (usize, usize)), usize)> + 'a {
-    [
-      (Dir::L, [x - 1, y]),
-      (Dir::R, [x + 1, y]),
-      (Dir::U, [x, y - 1]),
-      (Dir::D, [x, y + 1]),
-    ]
-    .into_iter()
-    .filter_map(move |(o_dir, [o_x, o_y])| {
-      // no need for bounds checking because the maze is always surrounded by a wall
-      let o = grid[y][x];
-      if o == '#' {
-        return None;
-      }
-      Some(((o_dir, (o_x, o_y)), if o_dir == dir { 1 } else { 1001 }))
-    })
-  }
+fn successors<'a>(
+  grid: &'a [Vec<char>],
+  &(dir, (x, y)): &(Dir, (usize, usize)),
+) -> impl Iterator<Item = ((Dir, (usize, usize)), usize)> + 'a {
+  [
+    (Dir::L, [x - 1, y]),
+    (Dir::R, [x + 1, y]),
+    (Dir::U, [x, y - 1]),
+    (Dir::D, [x, y + 1]),
+  ]
+  .into_iter()
+  .filter_map(move |(o_dir, [o_x, o_y])| {
+    // no need for bounds checking because the maze is always surrounded by a wall
+    let o = grid[y][x];
+    if o == '#' {
+      return None;
+    }
+    Some(((o_dir, (o_x, o_y)), if o_dir == dir { 1 } else { 1001 }))
+  })
+}
 
+/// Returns the lowest possible score along with every tile that's part of at least one path with
+/// that score.
+fn best_paths(
+  grid: &[Vec<char>],
+  start: (usize, usize),
+  end: (usize, usize),
+) -> (usize, FxHashSet<(usize, usize)>) {
   let path = astar(
     &(Dir::R, start),
-    |c| successors(&grid, c),
+    |c| successors(grid, c),
     |_| 1,
     |c| c.1 == end,
   )
   .unwrap();
 
-  println!("Part 1: {}", path.1);
-
   let min_cost = path.1;
-  let valid_tiles = Arc::new(Mutex::new(FxHashSet::default()));
-  let valid_tiles_clone = valid_tiles.clone();
+  let valid_tiles = Mutex::new(FxHashSet::default());
 
   let width = grid[0].len();
   (0..grid.len())
     .into_par_iter()
     .flat_map(|y| (0..width).into_par_iter().map(move |x| (x, y)))
-    .for_each(move |(x, y)| {
+    .for_each(|(x, y)| {
       let path = astar(
         &(Dir::R, start),
-        |c| successors(&grid, c),
+        |c| successors(grid, c),
         |_| 1,
         |c| c.1 == (x, y),
       );
@@ -90,7 +99,7 @@ pub fn solve() {
 
       let path = astar(
         path.0.last().unwrap(),
-        |c| successors(&grid, c),
+        |c| successors(grid, c),
         |_| 1,
         |c| c.1 == end,
       );
@@ -108,5 +117,33 @@ pub fn solve() {
       }
     });
 
-  println!("Part 2: {}", valid_tiles_clone.lock().unwrap().len())
+  (min_cost, valid_tiles.into_inner().unwrap())
+}
+
+pub fn solve() {
+  let grid = parse_input(INPUT);
+  let (start, end) = find_start_end(&grid);
+
+  let (min_cost, valid_tiles) = best_paths(&grid, start, end);
+
+  answer(1, min_cost);
+
+  answer(2, valid_tiles.len())
+}
+
+/// Writes an image of the maze to `path` with all tiles that are part of a best path highlighted.
+pub fn export_image(path: impl AsRef<Path>) -> io::Result<()> {
+  let grid = parse_input(INPUT);
+  let (start, end) = find_start_end(&grid);
+  let (_, valid_tiles) = best_paths(&grid, start, end);
+
+  let mut img = Image::from_grid(&grid, |_, _, &c| match c {
+    '#' => Rgb(70, 70, 80),
+    _ => Rgb(15, 15, 20),
+  })
+  .scale(6);
+  img.overlay(valid_tiles, Rgb(240, 200, 60));
+  img.set(start.0, start.1, Rgb(60, 200, 80));
+  img.set(end.0, end.1, Rgb(220, 60, 60));
+  img.save(path)
 }
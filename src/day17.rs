@@ -1,10 +1,13 @@
 use itertools::Itertools;
 use regex::Regex;
+#[cfg(feature = "z3")]
 use z3::{
   ast::{Ast, Bool, Int, BV},
   Solver,
 };
 
+use crate::runner::answer;
+
 pub const INPUT: &'static str = include_str!("../inputs/day17.txt");
 
 #[derive(Clone)]
@@ -87,12 +90,14 @@ impl Op {
   }
 }
 
+#[cfg(feature = "z3")]
 struct Outs<'a> {
   a: BV<'a>,
   out: BV<'a>,
   exit: Bool<'a>,
 }
 
+#[cfg(feature = "z3")]
 fn one_iter<'a>(ctx: &'a z3::Context, a: BV<'a>) -> Outs<'a> {
   // 2,4
   // b = a % 8
@@ -128,6 +133,7 @@ fn one_iter<'a>(ctx: &'a z3::Context, a: BV<'a>) -> Outs<'a> {
   Outs { a, out, exit }
 }
 
+#[cfg(feature = "z3")]
 fn check_z3() {
   let z3_conf = z3::Config::new();
   let ctx = z3::Context::new(&z3_conf);
@@ -142,6 +148,7 @@ fn check_z3() {
   assert_eq!(solver.check(), z3::SatResult::Sat);
 }
 
+#[cfg(feature = "z3")]
 fn do_z3(outputs: &[usize], lt: Option<usize>) -> Option<usize> {
   let z3_conf = z3::Config::new();
   let ctx = z3::Context::new(&z3_conf);
@@ -174,6 +181,66 @@ fn do_z3(outputs: &[usize], lt: Option<usize>) -> Option<usize> {
   out.as_u64().map(|u| u as usize)
 }
 
+/// Finds the smallest initial value of register A that makes the program output itself.
+#[cfg(feature = "z3")]
+fn find_quine_a(cpu: &Cpu) -> Option<usize> {
+  check_z3();
+
+  let mut lt = None;
+  loop {
+    match do_z3(&cpu.prog, lt) {
+      Some(res) => {
+        lt = Some(res);
+      },
+      None => return lt,
+    }
+  }
+}
+
+#[cfg(not(feature = "z3"))]
+fn run_with_a(cpu: &Cpu, a: usize) -> Vec<usize> {
+  let mut cpu = Cpu {
+    ip: 0,
+    a,
+    ..cpu.clone()
+  };
+  let mut out = Vec::new();
+  while cpu.tick(&mut out) {}
+  out
+}
+
+/// The program loops until A is zero, shifting A right by 3 bits and outputting one value each
+/// iteration with each output depending only on the current value of A.  So the last output only
+/// depends on the top 3 bits of A, the second-to-last on the top 6, and so on, which means A can be
+/// built up 3 bits at a time starting from the last output.
+///
+/// Trying the lowest bits first means the first full match found is the smallest.
+#[cfg(not(feature = "z3"))]
+fn find_quine_a_inner(cpu: &Cpu, a_prefix: usize, matched_count: usize) -> Option<usize> {
+  if matched_count == cpu.prog.len() {
+    return Some(a_prefix);
+  }
+
+  let expected = &cpu.prog[cpu.prog.len() - matched_count - 1..];
+  for low_bits in 0..8 {
+    let a = (a_prefix << 3) | low_bits;
+    if a == 0 {
+      continue;
+    }
+    if run_with_a(cpu, a) == expected {
+      if let Some(res) = find_quine_a_inner(cpu, a, matched_count + 1) {
+        return Some(res);
+      }
+    }
+  }
+
+  None
+}
+
+/// Finds the smallest initial value of register A that makes the program output itself.
+#[cfg(not(feature = "z3"))]
+fn find_quine_a(cpu: &Cpu) -> Option<usize> { find_quine_a_inner(cpu, 0, 0) }
+
 fn parse_input(input: &str) -> Cpu {
   let rgx =
     Regex::new(r#"Register A: (\d+)\nRegister B: (\d+)\nRegister C: (\d+)\n\nProgram: (.+)"#)
@@ -206,20 +273,25 @@ pub fn solve() {
 
   let out = out.iter().copied().join(",");
 
-  println!("Part 1: {out}");
+  answer(1, out);
 
-  check_z3();
+  answer(2, find_quine_a(&orig_cpu).unwrap());
+}
 
-  let mut lt = None;
-  loop {
-    match do_z3(&cpu.prog, lt) {
-      Some(res) => {
-        lt = Some(res);
-      },
-      None => {
-        println!("Part 2: {}", lt.unwrap());
-        return;
-      },
-    }
-  }
+#[test]
+fn example() {
+  let mut cpu =
+    parse_input("Register A: 729\nRegister B: 0\nRegister C: 0\n\nProgram: 0,1,5,4,3,0");
+  let mut out = Vec::new();
+  while cpu.tick(&mut out) {}
+  assert_eq!(out.iter().join(","), "4,6,3,5,6,3,5,2,1,0");
+}
+
+#[cfg(not(feature = "z3"))]
+#[test]
+fn quine_example() {
+  let cpu = parse_input("Register A: 2024\nRegister B: 0\nRegister C: 0\n\nProgram: 0,3,5,4,3,0");
+  assert_eq!(find_quine_a(&cpu), Some(117440));
+  // and nothing smaller works
+  assert!((1..117440).all(|a| run_with_a(&cpu, a) != cpu.prog));
 }
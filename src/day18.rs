@@ -1,5 +1,12 @@
+use std::{io, path::Path};
+
 use pathfinding::directed::astar;
 
+use crate::{
+  image::{Image, Rgb},
+  runner::answer,
+};
+
 pub const INPUT: &'static str = include_str!("../inputs/day18.txt");
 
 fn parse_input(input: &str) -> Vec<(usize, usize)> {
@@ -17,6 +24,10 @@ const WIDTH: usize = 71;
 const HEIGHT: usize = 71;
 
 fn pathfind(input: &[(usize, usize)], timestep: usize) -> Option<usize> {
+  find_path(input, timestep).map(|path| path.len())
+}
+
+fn find_path(input: &[(usize, usize)], timestep: usize) -> Option<Vec<(usize, usize)>> {
   let grid = get_grid(&input, timestep);
 
   let start_coord = (0, 0);
@@ -44,7 +55,7 @@ fn pathfind(input: &[(usize, usize)], timestep: usize) -> Option<usize> {
   };
 
   let end_coord = (WIDTH - 1, HEIGHT - 1);
-  astar::astar(&start_coord, |c| next_coord(*c), |_| 1, |c| *c == end_coord).map(|p| p.0.len())
+  astar::astar(&start_coord, |c| next_coord(*c), |_| 1, |c| *c == end_coord).map(|p| p.0)
 }
 
 fn get_grid(inputs: &[(usize, usize)], timestep: usize) -> Vec<Vec<bool>> {
@@ -64,14 +75,51 @@ pub fn solve() {
   let input = parse_input(INPUT);
 
   let part1 = pathfind(&input, 1024).unwrap();
-  println!("Part 1: {part1}");
+  answer(1, part1);
 
   for i in 0..input.len() {
     if pathfind(&input, i).is_some() {
       continue;
     }
 
-    println!("Part 2: {},{}", input[i].0, input[i].1);
+    answer(2, format!("{},{}", input[i].0, input[i].1));
     break;
   }
 }
+
+/// Writes an image of the memory space after `timestep` bytes have fallen to `path`, along with the
+/// shortest path through it if there is one.  If there isn't, the byte that first cut off the exit
+/// is highlighted instead, which may have fallen well before `timestep`.
+pub fn export_image(path: impl AsRef<Path>, timestep: usize) -> io::Result<()> {
+  let input = parse_input(INPUT);
+  let grid = get_grid(&input, timestep);
+
+  let mut img = Image::from_grid(&grid, |_, _, &corrupted| {
+    if corrupted {
+      Rgb(200, 60, 60)
+    } else {
+      Rgb(20, 20, 30)
+    }
+  })
+  .scale(8);
+  match find_path(&input, timestep) {
+    Some(path) => img.overlay(path, Rgb(240, 200, 60)),
+    None => {
+      // once the exit is cut off it stays that way, so binary search for the first timestep without
+      // a path.  There's always one with no bytes fallen.
+      let (mut open, mut blocked) = (0, timestep);
+      while blocked - open > 1 {
+        let mid = (open + blocked) / 2;
+        if pathfind(&input, mid).is_some() {
+          open = mid;
+        } else {
+          blocked = mid;
+        }
+      }
+      if let Some(&(x, y)) = blocked.checked_sub(1).and_then(|i| input.get(i)) {
+        img.set(x, y, Rgb(255, 255, 255));
+      }
+    },
+  }
+  img.save(path)
+}
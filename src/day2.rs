@@ -1,7 +1,11 @@
 pub const INPUT: &'static str = include_str!("../inputs/day2.txt");
 
-fn parse_input() -> Vec<Vec<isize>> {
-  INPUT
+use std::{fmt, ops::RangeInclusive};
+
+use crate::runner::answer;
+
+fn parse_input(input: &str) -> Vec<Vec<isize>> {
+  input
     .lines()
     .map(|l| {
       l.split_ascii_whitespace()
@@ -11,45 +15,361 @@ fn parse_input() -> Vec<Vec<isize>> {
     .collect()
 }
 
-fn row_safe(row: &[isize]) -> bool {
-  let increasing = row[1] > row[0];
+/// Decides whether reports are safe when up to `max_removals` levels can be removed from them.
+///
+/// A report is safe if its levels are all increasing or all decreasing, with the size of every step
+/// between adjacent levels in `steps`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dampener {
+  max_removals: usize,
+  steps: RangeInclusive<isize>,
+}
+
+impl Dampener {
+  pub fn new(max_removals: usize) -> Self {
+    Dampener {
+      max_removals,
+      steps: 1..=3,
+    }
+  }
+
+  /// Sets the allowed sizes of steps between adjacent levels.  Defaults to `1..=3`.
+  pub fn steps(mut self, steps: RangeInclusive<isize>) -> Self {
+    self.steps = steps;
+    self
+  }
+
+  pub fn is_safe(&self, row: &[isize]) -> bool { self.levels_to_remove(row).is_some() }
+
+  /// Returns the indices of the fewest levels that need to be removed from `row` to make it safe in
+  /// ascending order, or `None` if it can't be made safe with at most `max_removals` removals.
+  pub fn levels_to_remove(&self, row: &[isize]) -> Option<Vec<usize>> {
+    let increasing = self.levels_to_remove_directed(row, true);
+    let decreasing = self.levels_to_remove_directed(row, false);
+    match (increasing, decreasing) {
+      (Some(inc), Some(dec)) => Some(if dec.len() < inc.len() { dec } else { inc }),
+      (inc, dec) => inc.or(dec),
+    }
+  }
+
+  /// Finds the levels to keep with a DP over `(last kept level, removals so far)`.  Since only the
+  /// previous `max_removals + 1` levels can come right before a kept level, this is
+  /// `O(n * max_removals^2)`, which is linear for a fixed number of removals.
+  fn levels_to_remove_directed(&self, row: &[isize], increasing: bool) -> Option<Vec<usize>> {
+    const UNREACHABLE: usize = usize::MAX;
+    const FIRST_KEPT: usize = usize::MAX - 1;
+
+    let n = row.len();
+    // removing more levels than there are never helps
+    let k = self.max_removals.min(n);
+    if n == 0 {
+      return Some(Vec::new());
+    }
+
+    let step_ok = |last: isize, cur: isize| {
+      let step = if increasing { cur - last } else { last - cur };
+      self.steps.contains(&step)
+    };
+
+    // `prev[i * (k + 1) + r]` is the previously kept level if level `i` can be kept with `r` levels
+    // before it removed
+    let mut prev = vec![UNREACHABLE; n * (k + 1)];
+    // any of the first `k + 1` levels can be the first one kept, and a lone level is always safe
+    for i in 0..=k.min(n - 1) {
+      prev[i * (k + 1) + i] = FIRST_KEPT;
+    }
+
+    for i in 1..n {
+      for j in i.saturating_sub(k + 1)..i {
+        let skipped = i - j - 1;
+        if !step_ok(row[j], row[i]) {
+          continue;
+        }
+        for r in 0..=k - skipped {
+          if prev[j * (k + 1) + r] != UNREACHABLE {
+            let slot = &mut prev[i * (k + 1) + r + skipped];
+            if *slot == UNREACHABLE {
+              *slot = j;
+            }
+          }
+        }
+      }
+    }
+
+    // whichever kept level leaves the fewest total removals once everything after it is dropped
+    let (mut i, mut r) = (0..n)
+      .rev()
+      .take(k + 1)
+      .flat_map(|i| (0..=k - (n - 1 - i)).map(move |r| (i, r)))
+      .filter(|&(i, r)| prev[i * (k + 1) + r] != UNREACHABLE)
+      .min_by_key(|&(i, r)| r + (n - 1 - i))?;
 
-  for [last, cur] in row.array_windows::<2>().copied() {
-    let now_increasing = cur > last;
-    if increasing != now_increasing {
-      return false;
+    let mut removed = ((i + 1)..n).collect::<Vec<_>>();
+    loop {
+      let j = prev[i * (k + 1) + r];
+      if j == FIRST_KEPT {
+        removed.extend(0..i);
+        break;
+      }
+      removed.extend(j + 1..i);
+      r -= i - j - 1;
+      i = j;
     }
 
-    let diff = (cur - last).abs();
-    if diff < 1 || diff > 3 {
-      return false;
+    removed.sort_unstable();
+    Some(removed)
+  }
+
+  /// Finds the first problem with `row` as-is, without removing any levels.  The direction of the
+  /// report is set by its first nonzero step, since steps of 0 don't have one.
+  fn first_problem(&self, row: &[isize]) -> Verdict {
+    let mut increasing = None;
+
+    for (i, [last, cur]) in row.array_windows::<2>().copied().enumerate() {
+      let delta = cur - last;
+      let ix = i + 1;
+      if delta != 0 && *increasing.get_or_insert(delta > 0) != (delta > 0) {
+        return Verdict::DirectionFlip { ix };
+      }
+
+      let step = delta.abs();
+      if step < *self.steps.start() {
+        return Verdict::StepTooSmall { ix, delta };
+      }
+      if step > *self.steps.end() {
+        return Verdict::StepTooLarge { ix, delta };
+      }
     }
+
+    Verdict::Safe
   }
 
-  true
+  /// Explains why `row` is safe or unsafe.  If the row is unsafe as-is but removing levels fixes
+  /// it, the levels that were removed are reported instead of the original problem.
+  pub fn verdict(&self, row: &[isize]) -> Verdict {
+    match self.first_problem(row) {
+      Verdict::Safe => Verdict::Safe,
+      problem => match self.levels_to_remove(row) {
+        Some(removed) => Verdict::Repaired { removed },
+        None => problem,
+      },
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+  Safe,
+  /// The level at `ix` goes in the opposite direction from the first nonzero step
+  DirectionFlip {
+    ix: usize,
+  },
+  /// The step from the level before `ix` to the level at `ix` is too small
+  StepTooSmall {
+    ix: usize,
+    delta: isize,
+  },
+  /// The step from the level before `ix` to the level at `ix` is too large
+  StepTooLarge {
+    ix: usize,
+    delta: isize,
+  },
+  /// The report was unsafe but becomes safe once the levels at `removed` are dropped
+  Repaired {
+    removed: Vec<usize>,
+  },
+}
+
+impl fmt::Display for Verdict {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Verdict::Safe => write!(f, "safe"),
+      Verdict::DirectionFlip { ix } => write!(f, "direction flips at level {ix}"),
+      Verdict::StepTooSmall { ix, delta } => write!(f, "step too small at level {ix} ({delta:+})"),
+      Verdict::StepTooLarge { ix, delta } => write!(f, "step too large at level {ix} ({delta:+})"),
+      Verdict::Repaired { removed } => write!(f, "safe after removing levels {removed:?}"),
+    }
+  }
+}
+
+/// Counts of each kind of verdict across a set of reports
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerdictSummary {
+  pub safe: usize,
+  pub direction_flips: usize,
+  pub steps_too_small: usize,
+  pub steps_too_large: usize,
+  pub repaired: usize,
+}
+
+impl VerdictSummary {
+  pub fn add(&mut self, verdict: &Verdict) {
+    match verdict {
+      Verdict::Safe => self.safe += 1,
+      Verdict::DirectionFlip { .. } => self.direction_flips += 1,
+      Verdict::StepTooSmall { .. } => self.steps_too_small += 1,
+      Verdict::StepTooLarge { .. } => self.steps_too_large += 1,
+      Verdict::Repaired { .. } => self.repaired += 1,
+    }
+  }
+
+  pub fn total(&self) -> usize {
+    self.safe + self.direction_flips + self.steps_too_small + self.steps_too_large + self.repaired
+  }
+}
+
+impl<'a> FromIterator<&'a Verdict> for VerdictSummary {
+  fn from_iter<I: IntoIterator<Item = &'a Verdict>>(iter: I) -> Self {
+    let mut summary = VerdictSummary::default();
+    for verdict in iter {
+      summary.add(verdict);
+    }
+    summary
+  }
+}
+
+impl fmt::Display for VerdictSummary {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "safe: {}", self.safe)?;
+    writeln!(f, "repaired: {}", self.repaired)?;
+    writeln!(f, "direction flips: {}", self.direction_flips)?;
+    writeln!(f, "steps too small: {}", self.steps_too_small)?;
+    writeln!(f, "steps too large: {}", self.steps_too_large)?;
+    write!(f, "total: {}", self.total())
+  }
+}
+
+/// Returns the verdict for every report in `input`, in order.
+pub fn verdicts(input: &str, dampener: &Dampener) -> Vec<Verdict> {
+  parse_input(input)
+    .iter()
+    .map(|row| dampener.verdict(row))
+    .collect()
 }
 
 pub fn solve() {
-  let input = parse_input();
-
-  let safe_count = input.iter().filter(|row| row_safe(row)).count();
-  println!("Part 1: {safe_count}");
-
-  let mut safe_count = 0usize;
-  let iter = input.into_iter();
-  // brute-force; I don't care to try to keep track of the state for that stuff manually
-  'row: for row in iter {
-    for ix_to_remove in 0..row.len() {
-      let mut row = row.clone();
-      row.remove(ix_to_remove);
-      let is_safe = row_safe(&row);
-
-      if is_safe {
-        safe_count += 1;
-        continue 'row;
-      }
+  let input = parse_input(INPUT);
+
+  let strict = Dampener::new(0);
+  let safe_count = input.iter().filter(|row| strict.is_safe(row)).count();
+  answer(1, safe_count);
+
+  let dampener = Dampener::new(1);
+  let safe_count = input.iter().filter(|row| dampener.is_safe(row)).count();
+  answer(2, safe_count);
+}
+
+#[test]
+fn verdicts_for_example() {
+  let input = "7 6 4 2 1\n1 2 7 8 9\n9 7 6 2 1\n1 3 2 4 5\n8 6 4 4 1\n1 3 6 7 9\n";
+
+  assert_eq!(verdicts(input, &Dampener::new(0)), [
+    Verdict::Safe,
+    Verdict::StepTooLarge { ix: 2, delta: 5 },
+    Verdict::StepTooLarge { ix: 3, delta: -4 },
+    Verdict::DirectionFlip { ix: 2 },
+    Verdict::StepTooSmall { ix: 3, delta: 0 },
+    Verdict::Safe,
+  ]);
+
+  let verdicts = verdicts(input, &Dampener::new(1));
+  assert_eq!(verdicts[3], Verdict::Repaired { removed: vec![2] });
+  assert_eq!(
+    verdicts.iter().collect::<VerdictSummary>(),
+    VerdictSummary {
+      safe: 2,
+      direction_flips: 0,
+      steps_too_small: 0,
+      steps_too_large: 2,
+      repaired: 2,
     }
+  );
+}
+
+#[test]
+fn verdicts_with_zero_steps() {
+  let dampener = Dampener::new(1).steps(0..=2);
+  assert_eq!(dampener.verdict(&[1, 1, 2, 3]), Verdict::Safe);
+  assert_eq!(dampener.verdict(&[3, 3, 2, 2, 1]), Verdict::Safe);
+  assert_eq!(dampener.verdict(&[1, 1, 2, 0, 3]), Verdict::Repaired {
+    removed: vec![3]
+  });
+  assert_eq!(
+    Dampener::new(0).steps(0..=2).verdict(&[1, 1, 2, 0]),
+    Verdict::DirectionFlip { ix: 3 }
+  );
+}
+
+#[test]
+fn dampener_matches_brute_force() {
+  fn brute_force(
+    steps: &RangeInclusive<isize>,
+    row: &[isize],
+    max_removals: usize,
+  ) -> Option<usize> {
+    let safe = |levels: &[isize], sign: isize| {
+      levels
+        .array_windows::<2>()
+        .all(|[last, cur]| steps.contains(&((cur - last) * sign)))
+    };
+    // try every subset of removals, fewest first
+    (0..=max_removals.min(row.len())).find(|&removal_count| {
+      (0..1usize << row.len())
+        .filter(|mask| mask.count_ones() as usize == removal_count)
+        .any(|mask| {
+          let kept = (0..row.len())
+            .filter(|i| mask & (1 << i) == 0)
+            .map(|i| row[i])
+            .collect::<Vec<_>>();
+          safe(&kept, 1) || safe(&kept, -1)
+        })
+    })
   }
 
-  println!("Part 2: {safe_count}");
+  let mut state = 0x9e3779b97f4a7c15u64;
+  let mut rand = |n: u64| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    (state % n) as isize
+  };
+
+  // more removals than levels, which mustn't overflow or allocate for every allowed removal
+  let lenient = Dampener::new(usize::MAX);
+  assert_eq!(lenient.levels_to_remove(&[1, 9, 4, 20]), Some(vec![1, 3]));
+  assert_eq!(lenient.verdict(&[5, 5]), Verdict::Repaired {
+    removed: vec![0]
+  });
+
+  for max_removals in 0..=3 {
+    for steps in [1..=3, 0..=2, 2..=4] {
+      let dampener = Dampener::new(max_removals).steps(steps.clone());
+      for _ in 0..2000 {
+        let len = rand(9) as usize;
+        let mut row = vec![rand(20)];
+        for _ in 1..len {
+          row.push(row.last().unwrap() + rand(9) - 4);
+        }
+
+        let removed = dampener.levels_to_remove(&row);
+        assert_eq!(
+          removed.as_ref().map(|r| r.len()),
+          brute_force(&steps, &row, max_removals),
+          "{row:?} {max_removals} {steps:?}"
+        );
+        assert_eq!(
+          dampener.first_problem(&row) == Verdict::Safe,
+          brute_force(&steps, &row, 0).is_some(),
+          "{row:?} {steps:?}"
+        );
+        if let Some(removed) = removed {
+          let kept = (0..row.len())
+            .filter(|i| !removed.contains(i))
+            .map(|i| row[i])
+            .collect::<Vec<_>>();
+          assert!(Dampener::new(0).steps(steps.clone()).is_safe(&kept));
+        }
+      }
+    }
+  }
 }
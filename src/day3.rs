@@ -2,9 +2,15 @@
 
 use std::{
   fmt::Display,
+  io::{self, Read},
   simd::{cmp::SimdPartialEq, u8x16, u8x64},
 };
 
+use fxhash::FxHashMap;
+use rayon::prelude::*;
+
+use crate::runner::answer;
+
 pub const INPUT: &'static [u8] = include_bytes!("../inputs/day3.txt");
 
 fn parse_digit(c: u8) -> usize { (c - 48) as usize }
@@ -28,24 +34,130 @@ const DONT: [u8; 7] = [
   'd' as u8, 'o' as u8, 'n' as u8, '\'' as u8, 't' as u8, '(' as u8, ')' as u8,
 ];
 const DO: [u8; 4] = ['d' as u8, 'o' as u8, '(' as u8, ')' as u8];
-// shortest valid mul is `mul(1,1)` so 8 chars
-const MIN_VALID_MUL_LEN: usize = 8;
+// longest valid mul is `mul(123,123)` so 12 chars
+const MAX_VALID_MUL_LEN: usize = 12;
 
 pub fn parse_and_compute<const ENABLE_DO_STATE: bool>(input: &[u8]) -> usize {
-  let mut sum = 0usize;
-  let mut do_state = true;
+  parse_and_compute_from::<ENABLE_DO_STATE>(input, &mut true)
+}
+
+/// Like `parse_and_compute`, but starting in the given do/don't state and updating it to the state
+/// at the end of the input.
+fn parse_and_compute_from<const ENABLE_DO_STATE: bool>(input: &[u8], do_state: &mut bool) -> usize {
   let mut char_ix = 0usize;
 
+  // The unchecked reads in `scan` can look up to `MAX_VALID_MUL_LEN` bytes past the last candidate
+  // start index, so the fast path has to stop that far from the end of the input.
+  let mut sum = match input.len().checked_sub(MAX_VALID_MUL_LEN) {
+    Some(fast_path_end) => scan::<ENABLE_DO_STATE>(input, fast_path_end, &mut char_ix, do_state),
+    None => 0,
+  };
+
+  sum += scan_tail::<ENABLE_DO_STATE>(input.get(char_ix..).unwrap_or_default(), do_state);
+
+  sum
+}
+
+/// Finishes off the last few bytes of the input which are too close to the end for `scan` to read
+/// ahead of.
+///
+/// They get copied into a zero-padded buffer so that the same code can finish them off without
+/// reading out of bounds.  Zeros can't be part of any instruction, so partial instructions at the
+/// very end of the input are rejected as usual.
+fn scan_tail<const ENABLE_DO_STATE: bool>(tail: &[u8], do_state: &mut bool) -> usize {
+  debug_assert!(tail.len() <= MAX_VALID_MUL_LEN);
+  let mut padded_tail = [0u8; MAX_VALID_MUL_LEN * 2];
+  padded_tail[..tail.len()].copy_from_slice(tail);
+  let mut tail_char_ix = 0usize;
+  scan::<ENABLE_DO_STATE>(&padded_tail, tail.len(), &mut tail_char_ix, do_state)
+}
+
+/// Computes the same result as `parse_and_compute` for input that arrives in chunks, like from a
+/// file or socket, without needing all of it in memory at once.
+///
+/// Instructions can straddle chunk boundaries, so up to `MAX_VALID_MUL_LEN` bytes from the end of
+/// each chunk are held back until the next one arrives or the input is finished.
+pub struct StreamingEvaluator<const ENABLE_DO_STATE: bool> {
+  buf: Vec<u8>,
+  sum: usize,
+  do_state: bool,
+}
+
+impl<const ENABLE_DO_STATE: bool> Default for StreamingEvaluator<ENABLE_DO_STATE> {
+  fn default() -> Self {
+    StreamingEvaluator {
+      buf: Vec::new(),
+      sum: 0,
+      do_state: true,
+    }
+  }
+}
+
+impl<const ENABLE_DO_STATE: bool> StreamingEvaluator<ENABLE_DO_STATE> {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn feed(&mut self, chunk: &[u8]) {
+    self.buf.extend_from_slice(chunk);
+
+    let Some(scan_end) = self.buf.len().checked_sub(MAX_VALID_MUL_LEN) else {
+      return;
+    };
+    let mut char_ix = 0usize;
+    self.sum += scan::<ENABLE_DO_STATE>(&self.buf, scan_end, &mut char_ix, &mut self.do_state);
+    self.buf.drain(..char_ix.min(self.buf.len()));
+  }
+
+  /// Processes whatever input is left over and returns the final result.
+  pub fn finish(mut self) -> usize {
+    self.sum + scan_tail::<ENABLE_DO_STATE>(&self.buf, &mut self.do_state)
+  }
+}
+
+/// Reads all of `reader` through a `StreamingEvaluator`.
+pub fn parse_and_compute_reader<const ENABLE_DO_STATE: bool>(
+  mut reader: impl Read,
+) -> io::Result<usize> {
+  let mut evaluator = StreamingEvaluator::<ENABLE_DO_STATE>::new();
+  let mut chunk = vec![0u8; 64 * 1024];
+  loop {
+    match reader.read(&mut chunk) {
+      Ok(0) => break,
+      Ok(n) => evaluator.feed(&chunk[..n]),
+      Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+      Err(err) => return Err(err),
+    }
+  }
+  Ok(evaluator.finish())
+}
+
+/// Sums up all valid `mul`s that start in `input[*char_ix..scan_end]`, updating `char_ix` and
+/// `do_state` to reflect where scanning stopped.
+///
+/// `input` must extend at least `MAX_VALID_MUL_LEN` bytes past `scan_end` since the parsing code
+/// reads ahead without bounds checks.
+#[inline(always)]
+fn scan<const ENABLE_DO_STATE: bool>(
+  input: &[u8],
+  scan_end: usize,
+  char_ix_out: &mut usize,
+  do_state_out: &mut bool,
+) -> usize {
+  assert!(scan_end + MAX_VALID_MUL_LEN <= input.len());
+
+  let mut sum = 0usize;
+  let mut do_state = *do_state_out;
+  let mut char_ix = *char_ix_out;
+
   'outer: loop {
-    if char_ix >= input.len() - MIN_VALID_MUL_LEN {
-      return sum;
+    if char_ix >= scan_end {
+      break 'outer;
     }
 
     // For part 2, when the "do" mode is set to "don't", we only care about finding `d` characters.
     //
     // Since d's are so much sparser in the inputs than m's, there's a decent chance it will be
     // closer to 64 chars ahead than 16, and the overhead of reading further tends to be worth it.
-    if ENABLE_DO_STATE && !do_state && char_ix < input.len() - (64 + 1) {
+    if ENABLE_DO_STATE && !do_state && char_ix + 64 + 1 < input.len() {
       let vector = u8x64::from_slice(&input[char_ix..char_ix + 64]);
 
       let mask = vector.simd_eq(u8x64::splat('d' as u8));
@@ -62,7 +174,7 @@ pub fn parse_and_compute<const ENABLE_DO_STATE: bool>(input: &[u8]) -> usize {
     }
     // Try to find the first relavant start character in the input by checking 16 at a time and then
     // selecting the index of the first match
-    else if char_ix < input.len() - (16 + 1) {
+    else if char_ix + 16 + 1 < input.len() {
       let vector = u8x16::from_slice(&input[char_ix..char_ix + 16]);
 
       let combined_mask = if ENABLE_DO_STATE {
@@ -94,15 +206,15 @@ pub fn parse_and_compute<const ENABLE_DO_STATE: bool>(input: &[u8]) -> usize {
       let mut c = unsafe { *input.get_unchecked(char_ix) };
       while c != 'm' as u8 && (!ENABLE_DO_STATE || c != 'd' as u8) {
         char_ix += 1;
-        if char_ix >= input.len() {
-          return sum;
+        if char_ix >= scan_end {
+          break 'outer;
         }
         c = unsafe { *input.get_unchecked(char_ix) };
       }
     }
 
-    if char_ix >= input.len() - MIN_VALID_MUL_LEN {
-      return sum;
+    if char_ix >= scan_end {
+      break 'outer;
     }
 
     // don't bother parsing out this mul if the do flag is not set
@@ -118,108 +230,118 @@ pub fn parse_and_compute<const ENABLE_DO_STATE: bool>(input: &[u8]) -> usize {
       //  * arguments can have at between 1 and 3 digits
       //  * arguments are positive integers
 
-      let first_num;
-      let second_num;
+      let product = 'mul: {
+        let first_num;
+        let second_num;
 
-      let mut d0;
-      let mut d1;
-      let mut d2;
-
-      // first char after `mul(` must be a digit
-      let mut c = unsafe { *input.get_unchecked(char_ix) };
-      char_ix += 1;
-      if c >= '0' as u8 && c <= '9' as u8 {
-        d0 = parse_digit(c);
-      } else {
-        continue;
-      }
+        let mut d0;
+        let mut d1;
+        let mut d2;
 
-      // next char `mul(1_` can be either digit or comma
-      c = unsafe { *input.get_unchecked(char_ix) };
-      char_ix += 1;
-
-      if c >= '0' as u8 && c <= '9' as u8 {
-        d1 = parse_digit(c);
+        // first char after `mul(` must be a digit
+        let mut c = unsafe { *input.get_unchecked(char_ix) };
+        char_ix += 1;
+        if c >= '0' as u8 && c <= '9' as u8 {
+          d0 = parse_digit(c);
+        } else {
+          break 'mul None;
+        }
 
+        // next char `mul(1_` can be either digit or comma
         c = unsafe { *input.get_unchecked(char_ix) };
         char_ix += 1;
 
-        // next char `mul(12_` can also be either digit or comma
         if c >= '0' as u8 && c <= '9' as u8 {
-          d2 = parse_digit(c);
+          d1 = parse_digit(c);
 
           c = unsafe { *input.get_unchecked(char_ix) };
           char_ix += 1;
 
-          // next char `mul(123_` MUST be a comma if this mul is valid
-          if c != ',' as u8 {
-            continue 'outer;
-          }
+          // next char `mul(12_` can also be either digit or comma
+          if c >= '0' as u8 && c <= '9' as u8 {
+            d2 = parse_digit(c);
+
+            c = unsafe { *input.get_unchecked(char_ix) };
+            char_ix += 1;
+
+            // next char `mul(123_` MUST be a comma if this mul is valid
+            if c != ',' as u8 {
+              break 'mul None;
+            }
 
-          first_num = add_num(&[d0, d1, d2]);
+            first_num = add_num(&[d0, d1, d2]);
+          } else if c == ',' as u8 {
+            first_num = add_num(&[d0, d1]);
+          } else {
+            break 'mul None;
+          }
         } else if c == ',' as u8 {
-          first_num = add_num(&[d0, d1]);
+          first_num = d0;
         } else {
-          continue 'outer;
+          break 'mul None;
         }
-      } else if c == ',' as u8 {
-        first_num = d0;
-      } else {
-        continue 'outer;
-      }
 
-      c = unsafe { *input.get_unchecked(char_ix) };
-      char_ix += 1;
-
-      // at this point, we've successfully parsed a valid first argument number followed by a comma.
-      //
-      // we now have to parse out a valid second argument followed by a closing parenthesis.
-
-      // next character `mul(123,_` must be a digit
-      if c >= '0' as u8 && c <= '9' as u8 {
-        d0 = parse_digit(c);
-      } else {
-        continue;
-      }
+        c = unsafe { *input.get_unchecked(char_ix) };
+        char_ix += 1;
 
-      // finish parsing second arg.  Assuming that args have at most 3 chars, so take at most two
-      // more digits followed by a `)`
+        // at this point, we've successfully parsed a valid first argument number followed by a
+        // comma.
+        //
+        // we now have to parse out a valid second argument followed by a closing parenthesis.
 
-      c = unsafe { *input.get_unchecked(char_ix) };
-      char_ix += 1;
+        // next character `mul(123,_` must be a digit
+        if c >= '0' as u8 && c <= '9' as u8 {
+          d0 = parse_digit(c);
+        } else {
+          break 'mul None;
+        }
 
-      // next character `mul(123,1_` can be either digit or `)`
-      if c >= '0' as u8 && c <= '9' as u8 {
-        d1 = parse_digit(c);
+        // finish parsing second arg.  Assuming that args have at most 3 chars, so take at most two
+        // more digits followed by a `)`
 
         c = unsafe { *input.get_unchecked(char_ix) };
         char_ix += 1;
 
-        // next char `mul(123,12_` can also be either digit or `)`
+        // next character `mul(123,1_` can be either digit or `)`
         if c >= '0' as u8 && c <= '9' as u8 {
-          d2 = parse_digit(c);
+          d1 = parse_digit(c);
 
           c = unsafe { *input.get_unchecked(char_ix) };
           char_ix += 1;
 
-          // next char `mul(123,123_` MUST be a `)` if this mul is valid
-          if c != ')' as u8 {
-            continue 'outer;
-          }
+          // next char `mul(123,12_` can also be either digit or `)`
+          if c >= '0' as u8 && c <= '9' as u8 {
+            d2 = parse_digit(c);
+
+            c = unsafe { *input.get_unchecked(char_ix) };
+            char_ix += 1;
+
+            // next char `mul(123,123_` MUST be a `)` if this mul is valid
+            if c != ')' as u8 {
+              break 'mul None;
+            }
 
-          second_num = add_num(&[d0, d1, d2]);
+            second_num = add_num(&[d0, d1, d2]);
+          } else if c == ')' as u8 {
+            second_num = add_num(&[d0, d1]);
+          } else {
+            break 'mul None;
+          }
         } else if c == ')' as u8 {
-          second_num = add_num(&[d0, d1]);
+          second_num = d0;
         } else {
-          continue 'outer;
+          break 'mul None;
         }
-      } else if c == ')' as u8 {
-        second_num = d0;
-      } else {
-        continue 'outer;
-      }
 
-      sum += first_num * second_num;
+        Some(first_num * second_num)
+      };
+
+      match product {
+        Some(product) => sum += product,
+        // the character that made this `mul` invalid could be the start of another instruction, so
+        // resume scanning from it
+        None => char_ix -= 1,
+      }
     } else if ENABLE_DO_STATE && do_state && input.get(char_ix..char_ix + DONT.len()) == Some(&DONT)
     {
       do_state = false;
@@ -231,14 +353,668 @@ pub fn parse_and_compute<const ENABLE_DO_STATE: bool>(input: &[u8]) -> usize {
       char_ix += 1;
     }
   }
+
+  *char_ix_out = char_ix;
+  *do_state_out = do_state;
+  sum
+}
+
+/// Part 2 results for one chunk of the input, for each possible do/don't state at its start
+#[derive(Clone, Copy, Debug)]
+struct ChunkSummary {
+  /// Indexed by the starting do state
+  sums: [usize; 2],
+  end_states: [bool; 2],
+}
+
+/// Computes part 2 for the instructions starting in `input[start..end]`.  Instructions that
+/// straddle `end` are handled by reading past it, and since none of the instructions contain an `m`
+/// or `d` past their first character, the next chunk can't mistake the rest of them for
+/// instructions of its own.
+fn summarize_chunk(input: &[u8], start: usize, end: usize) -> ChunkSummary {
+  let run = |mut do_state: bool| {
+    let sum = if end == input.len() {
+      parse_and_compute_from::<true>(&input[start..], &mut do_state)
+    } else {
+      let mut char_ix = 0usize;
+      scan::<true>(&input[start..], end - start, &mut char_ix, &mut do_state)
+    };
+    (sum, do_state)
+  };
+
+  let (disabled_sum, disabled_end) = run(false);
+  let (enabled_sum, enabled_end) = run(true);
+  ChunkSummary {
+    sums: [disabled_sum, enabled_sum],
+    end_states: [disabled_end, enabled_end],
+  }
+}
+
+/// Computes part 2 by splitting the input into chunks of about `chunk_len` bytes and summarizing
+/// them in parallel.  Since a chunk's result only depends on whether instructions are enabled at
+/// its start, each one is computed for both cases and then a scan over the summaries picks out the
+/// right one for each chunk.
+pub fn part2_chunked(input: &[u8], chunk_len: usize) -> usize {
+  let chunk_len = chunk_len.max(1);
+
+  // every chunk but the last needs `MAX_VALID_MUL_LEN` bytes after it for `scan` to read ahead into
+  let mut bounds = (0..input.len().saturating_sub(MAX_VALID_MUL_LEN))
+    .step_by(chunk_len)
+    .collect::<Vec<_>>();
+  if bounds.is_empty() {
+    bounds.push(0);
+  }
+  bounds.push(input.len());
+
+  let summaries = bounds
+    .par_windows(2)
+    .map(|bounds| summarize_chunk(input, bounds[0], bounds[1]))
+    .collect::<Vec<_>>();
+
+  let mut do_state = true;
+  let mut sum = 0usize;
+  for summary in &summaries {
+    sum += summary.sums[do_state as usize];
+    do_state = summary.end_states[do_state as usize];
+  }
+  sum
+}
+
+/// Don't bother splitting the input finer than this since each chunk gets scanned twice
+const MIN_PARALLEL_CHUNK_LEN: usize = 4096;
+
+/// Computes part 2 in parallel with one chunk per thread.
+pub fn part2_parallel(input: &[u8]) -> usize {
+  let chunk_len = input
+    .len()
+    .div_ceil(rayon::current_num_threads())
+    .max(MIN_PARALLEL_CHUNK_LEN);
+  part2_chunked(input, chunk_len)
+}
+
+// The fast path above fuses scanning, parsing and evaluation and only knows about `mul`, `do` and
+// `don't`.  What follows is the general version: a tokenizer that turns the corrupted memory into a
+// stream of instructions, and an interpreter that runs them with pluggable handlers.
+
+/// Arguments longer than this can't be parsed into a `u64`, so calls with them are treated as
+/// corrupted
+const MAX_ARG_DIGITS: usize = 18;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction<'a> {
+  Mul(usize, usize),
+  Do,
+  Dont,
+  /// Something that looks like a call, `name(1,2,...)`, but isn't one of the known instructions
+  /// (or is one of their names with the wrong arguments).
+  Other {
+    name: &'a str,
+    args: Vec<u64>,
+  },
+}
+
+impl Instruction<'_> {
+  pub fn name(&self) -> &str {
+    match self {
+      Instruction::Mul(..) => "mul",
+      Instruction::Do => "do",
+      Instruction::Dont => "don't",
+      Instruction::Other { name, .. } => name,
+    }
+  }
+}
+
+/// An instruction along with the byte range of the input it was parsed from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+  pub offset: usize,
+  pub len: usize,
+  pub instruction: Instruction<'a>,
+}
+
+fn is_name_char(c: u8) -> bool { c.is_ascii_lowercase() || c == '\'' as u8 || c == '_' as u8 }
+
+/// Parses the comma-separated arguments of a call starting just after its `(`, returning them along
+/// with the index just past the closing `)`.  Each argument comes with its number of digits, since
+/// leading zeros count towards the fast path's limit of 3.
+fn parse_args(input: &[u8], mut ix: usize) -> Option<(Vec<(u64, usize)>, usize)> {
+  let mut args = Vec::new();
+  if input.get(ix) == Some(&(')' as u8)) {
+    return Some((args, ix + 1));
+  }
+
+  loop {
+    let digit_count = input[ix..]
+      .iter()
+      .take_while(|c| c.is_ascii_digit())
+      .count();
+    if digit_count == 0 || digit_count > MAX_ARG_DIGITS {
+      return None;
+    }
+    let arg = input[ix..ix + digit_count]
+      .iter()
+      .fold(0u64, |acc, &c| acc * 10 + (c - '0' as u8) as u64);
+    args.push((arg, digit_count));
+    ix += digit_count;
+
+    match input.get(ix).copied() {
+      Some(b',') => ix += 1,
+      Some(b')') => return Some((args, ix + 1)),
+      _ => return None,
+    }
+  }
+}
+
+/// Produces the instructions in the corrupted memory in order.  This accepts exactly the same
+/// `mul`, `do` and `don't` instructions as the fast path: anything ending in `mul` followed by two
+/// arguments of 1 to 3 digits, and anything ending in `do` or `don't` followed by `()`.
+pub struct Tokenizer<'a> {
+  input: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+  pub fn new(input: &'a [u8]) -> Self { Tokenizer { input, pos: 0 } }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+  type Item = Token<'a>;
+
+  fn next(&mut self) -> Option<Token<'a>> {
+    loop {
+      let paren_ix = self.pos
+        + self.input[self.pos..]
+          .iter()
+          .position(|&c| c == '(' as u8)?;
+      let name_len = self.input[self.pos..paren_ix]
+        .iter()
+        .rev()
+        .take_while(|&&c| is_name_char(c))
+        .count();
+      let name = &self.input[paren_ix - name_len..paren_ix];
+
+      let Some((args, end)) = parse_args(self.input, paren_ix + 1) else {
+        self.pos = paren_ix + 1;
+        continue;
+      };
+      self.pos = end;
+
+      let (start, instruction) = match args[..] {
+        [(a, a_digits), (b, b_digits)]
+          if name.ends_with(b"mul") && a_digits <= 3 && b_digits <= 3 =>
+          (paren_ix - 3, Instruction::Mul(a as usize, b as usize)),
+        [] if name.ends_with(b"don't") => (paren_ix - 5, Instruction::Dont),
+        [] if name.ends_with(b"do") => (paren_ix - 2, Instruction::Do),
+        _ if name.is_empty() => continue,
+        _ => (paren_ix - name_len, Instruction::Other {
+          // names are made up of ASCII only
+          name: std::str::from_utf8(name).unwrap(),
+          args: args.into_iter().map(|(arg, _)| arg).collect(),
+        }),
+      };
+
+      return Some(Token {
+        offset: start,
+        len: end - start,
+        instruction,
+      });
+    }
+  }
+}
+
+pub fn tokenize(input: &[u8]) -> Tokenizer<'_> { Tokenizer::new(input) }
+
+/// The state that instruction handlers act on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Machine {
+  /// Cleared by `don't()` and set by `do()`.  Handlers are responsible for checking it themselves.
+  pub enabled: bool,
+  pub sum: usize,
+}
+
+impl Default for Machine {
+  fn default() -> Self {
+    Machine {
+      enabled: true,
+      sum: 0,
+    }
+  }
+}
+
+pub type Handler<'h> = Box<dyn FnMut(&mut Machine, &Instruction) + 'h>;
+
+/// The result of running an instruction stream
+#[derive(Debug)]
+pub struct Execution<'a> {
+  pub machine: Machine,
+  /// Tokens that no handler was registered for, in order
+  pub unknown: Vec<Token<'a>>,
+}
+
+/// Runs instruction streams by dispatching each instruction to the handler registered for its name.
+#[derive(Default)]
+pub struct Interpreter<'h> {
+  handlers: FxHashMap<String, Handler<'h>>,
+}
+
+impl<'h> Interpreter<'h> {
+  /// Creates an interpreter with no handlers, which reports every instruction as unknown.
+  pub fn new() -> Self { Self::default() }
+
+  /// Handles `mul` and ignores `do` and `don't`.
+  pub fn part1() -> Self {
+    Self::new()
+      .handler("mul", |machine, instruction| {
+        if let Instruction::Mul(a, b) = instruction {
+          machine.sum += a * b;
+        }
+      })
+      .handler("do", |_, _| {})
+      .handler("don't", |_, _| {})
+  }
+
+  /// Handles `mul`, only counting it while enabled, along with `do` and `don't`.
+  pub fn part2() -> Self {
+    Self::new()
+      .handler("mul", |machine, instruction| {
+        if let Instruction::Mul(a, b) = instruction {
+          if machine.enabled {
+            machine.sum += a * b;
+          }
+        }
+      })
+      // `don't(1)` and the like share the names but aren't real instructions
+      .handler("do", |machine, instruction| {
+        if *instruction == Instruction::Do {
+          machine.enabled = true;
+        }
+      })
+      .handler("don't", |machine, instruction| {
+        if *instruction == Instruction::Dont {
+          machine.enabled = false;
+        }
+      })
+  }
+
+  /// Registers `handler` for all instructions named `name`, replacing any existing handler.
+  pub fn handler(
+    mut self,
+    name: impl Into<String>,
+    handler: impl FnMut(&mut Machine, &Instruction) + 'h,
+  ) -> Self {
+    self.handlers.insert(name.into(), Box::new(handler));
+    self
+  }
+
+  pub fn run<'a>(&mut self, tokens: impl IntoIterator<Item = Token<'a>>) -> Execution<'a> {
+    self.run_from(Machine::default(), tokens)
+  }
+
+  /// Runs `tokens` starting from `machine` rather than the default state.
+  pub fn run_from<'a>(
+    &mut self,
+    mut machine: Machine,
+    tokens: impl IntoIterator<Item = Token<'a>>,
+  ) -> Execution<'a> {
+    let mut unknown = Vec::new();
+    for token in tokens {
+      match self.handlers.get_mut(token.instruction.name()) {
+        Some(handler) => handler(&mut machine, &token.instruction),
+        None => unknown.push(token),
+      }
+    }
+
+    Execution { machine, unknown }
+  }
+}
+
+// Span report: every place in the input that starts like an instruction, along with whether it was
+// accepted and why not if it wasn't.  Useful for checking the hand-written state machine in `scan`
+// against a straightforward reading of the rules.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpanKind {
+  Mul,
+  Do,
+  Dont,
+}
+
+impl SpanKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      SpanKind::Mul => "mul",
+      SpanKind::Do => "do",
+      SpanKind::Dont => "don't",
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+  /// An argument had more than 3 digits
+  TooManyDigits,
+  /// An argument had no digits at all
+  MissingDigits,
+  /// The first argument wasn't followed by a `,`
+  MissingComma,
+  /// The instruction wasn't closed with a `)`
+  MissingParen,
+  /// A valid `mul` that came after a `don't()`
+  DisabledByDont,
+}
+
+impl Rejection {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Rejection::TooManyDigits => "too_many_digits",
+      Rejection::MissingDigits => "missing_digits",
+      Rejection::MissingComma => "missing_comma",
+      Rejection::MissingParen => "missing_paren",
+      Rejection::DisabledByDont => "disabled_by_dont",
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+  pub start: usize,
+  /// Exclusive.  For rejected spans, this includes the byte that caused the rejection.
+  pub end: usize,
+  pub kind: SpanKind,
+  /// The product of the arguments for `mul`s that parsed successfully, even if they were disabled
+  pub product: Option<usize>,
+  pub rejection: Option<Rejection>,
+}
+
+impl Span {
+  pub fn accepted(&self) -> bool { self.rejection.is_none() }
+}
+
+/// Parses a 1 to 3 digit argument starting at `ix` followed by `terminator`, returning its value
+/// and the index after the terminator or the reason it's invalid and the index after the byte that
+/// made it so.
+fn parse_span_arg(
+  input: &[u8],
+  ix: usize,
+  terminator: u8,
+  missing_terminator: Rejection,
+) -> Result<(usize, usize), (Rejection, usize)> {
+  let digit_count = input[ix..]
+    .iter()
+    .take(4)
+    .take_while(|c| c.is_ascii_digit())
+    .count();
+  let end_of = |ix: usize| (ix + 1).min(input.len());
+  match digit_count {
+    0 => return Err((Rejection::MissingDigits, end_of(ix))),
+    4 => return Err((Rejection::TooManyDigits, end_of(ix + 3))),
+    _ => (),
+  }
+
+  let val = input[ix..ix + digit_count]
+    .iter()
+    .fold(0, |acc, &c| acc * 10 + parse_digit(c));
+  let terminator_ix = ix + digit_count;
+  if input.get(terminator_ix) == Some(&terminator) {
+    Ok((val, terminator_ix + 1))
+  } else {
+    Err((missing_terminator, end_of(terminator_ix)))
+  }
+}
+
+/// Finds every `mul(`, `do(` and `don't(` in `input` and reports whether it was accepted.  If
+/// `enable_do_state` is set, `mul`s following a `don't()` are rejected as they are in part 2.
+pub fn span_report(input: &[u8], enable_do_state: bool) -> Vec<Span> {
+  const DO_PREFIX: &[u8] = b"do(";
+  const DONT_PREFIX: &[u8] = b"don't(";
+
+  let mut spans = Vec::new();
+  let mut enabled = true;
+
+  for start in 0..input.len() {
+    let rest = &input[start..];
+    let (kind, args_start) = if rest.starts_with(&MUL) {
+      (SpanKind::Mul, start + MUL.len())
+    } else if rest.starts_with(DO_PREFIX) {
+      (SpanKind::Do, start + DO_PREFIX.len())
+    } else if rest.starts_with(DONT_PREFIX) {
+      (SpanKind::Dont, start + DONT_PREFIX.len())
+    } else {
+      continue;
+    };
+
+    let parsed = match kind {
+      SpanKind::Mul => parse_span_arg(input, args_start, ',' as u8, Rejection::MissingComma)
+        .and_then(|(a, ix)| {
+          parse_span_arg(input, ix, ')' as u8, Rejection::MissingParen).map(|(b, ix)| (a * b, ix))
+        })
+        .map(|(product, end)| (Some(product), end)),
+      SpanKind::Do | SpanKind::Dont =>
+        if input.get(args_start) == Some(&(')' as u8)) {
+          Ok((None, args_start + 1))
+        } else {
+          Err((Rejection::MissingParen, (args_start + 1).min(input.len())))
+        },
+    };
+
+    let span = match parsed {
+      Ok((product, end)) => {
+        let rejection = match kind {
+          SpanKind::Mul if enable_do_state && !enabled => Some(Rejection::DisabledByDont),
+          SpanKind::Mul => None,
+          SpanKind::Do => {
+            enabled = true;
+            None
+          },
+          SpanKind::Dont => {
+            enabled = false;
+            None
+          },
+        };
+        Span {
+          start,
+          end,
+          kind,
+          product,
+          rejection,
+        }
+      },
+      Err((rejection, end)) => Span {
+        start,
+        end,
+        kind,
+        product: None,
+        rejection: Some(rejection),
+      },
+    };
+    spans.push(span);
+  }
+
+  spans
+}
+
+/// Writes `spans` as JSON lines, one object per span.
+pub fn write_span_report(spans: &[Span], mut out: impl io::Write) -> io::Result<()> {
+  for span in spans {
+    let product = span
+      .product
+      .map(|product| product.to_string())
+      .unwrap_or_else(|| "null".to_owned());
+    let rejection = span
+      .rejection
+      .map(|rejection| format!("\"{}\"", rejection.name()))
+      .unwrap_or_else(|| "null".to_owned());
+    writeln!(
+      out,
+      r#"{{"start":{},"end":{},"kind":"{}","accepted":{},"product":{product},"rejection":{rejection}}}"#,
+      span.start,
+      span.end,
+      span.kind.name(),
+      span.accepted(),
+    )?;
+  }
+  Ok(())
+}
+
+/// Renders `input` for the terminal with accepted spans in green, disabled `mul`s in yellow, and
+/// other rejected spans in red.  Where spans overlap, the later one wins.
+pub fn highlight_spans(input: &[u8], spans: &[Span]) -> String {
+  const GREEN: &str = "\x1b[32m";
+  const YELLOW: &str = "\x1b[33m";
+  const RED: &str = "\x1b[31m";
+  const DIM: &str = "\x1b[2m";
+
+  let mut colors = vec![DIM; input.len()];
+  for span in spans {
+    let color = match span.rejection {
+      None => GREEN,
+      Some(Rejection::DisabledByDont) => YELLOW,
+      Some(_) => RED,
+    };
+    colors[span.start..span.end].fill(color);
+  }
+
+  let mut out = String::new();
+  let mut cur_color = None;
+  for (&c, &color) in input.iter().zip(&colors) {
+    if cur_color != Some(color) {
+      out.push_str("\x1b[0m");
+      out.push_str(color);
+      cur_color = Some(color);
+    }
+    out.push(c as char);
+  }
+  out.push_str("\x1b[0m");
+  out
 }
 
 pub fn solve() {
   let out = parse_and_compute::<false>(INPUT);
-  println!("Part 1: {out}");
+  answer(1, out);
 
   let out = parse_and_compute::<true>(INPUT);
-  println!("Part 2: {out}");
+  answer(2, out);
 }
 
 pub fn run(input: &[u8]) -> impl Display { parse_and_compute::<false>(input) }
+
+#[test]
+fn instructions_at_end_of_input() {
+  assert_eq!(parse_and_compute::<false>(b""), 0);
+  assert_eq!(parse_and_compute::<false>(b"mul(2,3)"), 6);
+  assert_eq!(parse_and_compute::<false>(b"mul(123,4"), 0);
+  assert_eq!(
+    parse_and_compute::<true>(b"xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))"),
+    48
+  );
+}
+
+#[test]
+fn instruction_inside_rejected_mul() {
+  // the byte that makes a `mul` invalid can be the start of the next instruction.  These used to
+  // come out as 0, 0 and 6
+  assert_eq!(parse_and_compute::<false>(b"mul(1,mul(2,3))"), 6);
+  assert_eq!(parse_and_compute::<false>(b"mul(12mul(2,3)"), 6);
+  assert_eq!(parse_and_compute::<true>(b"mul(4,don't()mul(2,3)"), 0);
+}
+
+#[test]
+fn general_paths_match_fast_path() {
+  let fragments = "mul(|mul|do()|don't()|do|don't|(|)|,|1|23|456|7890|0001|0005|x|what()|from(1,\
+                   2)| |m|d|\n|mul(1,|mul(12|mul(3,45"
+    .split('|')
+    .collect::<Vec<_>>();
+
+  let mut state = 0xdeadbeefu64;
+  let mut rand = |n: usize| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state as usize % n
+  };
+
+  for _ in 0..20_000 {
+    let len = rand(120);
+    let input = (0..len)
+      .flat_map(|_| fragments[rand(fragments.len())].bytes())
+      .collect::<Vec<u8>>();
+
+    let tokens = tokenize(&input).collect::<Vec<_>>();
+    for token in &tokens {
+      let text = &input[token.offset..token.offset + token.len];
+      assert!(text.ends_with(b")") && text.starts_with(token.instruction.name().as_bytes()));
+    }
+
+    let p1 = Interpreter::part1().run(tokens.iter().cloned());
+    let p2 = Interpreter::part2().run(tokens.iter().cloned());
+    let input_str = String::from_utf8_lossy(&input);
+    assert_eq!(
+      p1.machine.sum,
+      parse_and_compute::<false>(&input),
+      "{input_str}"
+    );
+    assert_eq!(
+      p2.machine.sum,
+      parse_and_compute::<true>(&input),
+      "{input_str}"
+    );
+
+    let mut streaming = StreamingEvaluator::<true>::new();
+    let mut rest = &input[..];
+    while !rest.is_empty() {
+      let (chunk, remaining) = rest.split_at(rand(rest.len().min(100)) + 1);
+      streaming.feed(chunk);
+      rest = remaining;
+    }
+    assert_eq!(streaming.finish(), p2.machine.sum, "{input_str}");
+    assert_eq!(
+      parse_and_compute_reader::<false>(&input[..]).unwrap(),
+      p1.machine.sum
+    );
+
+    let spans = span_report(&input, true);
+    let accepted_sum = spans
+      .iter()
+      .filter(|span| span.accepted())
+      .filter_map(|span| span.product)
+      .sum::<usize>();
+    assert_eq!(accepted_sum, p2.machine.sum, "{input_str}");
+
+    let chunk_len = rand(30) + 1;
+    assert_eq!(
+      part2_chunked(&input, chunk_len),
+      p2.machine.sum,
+      "{input_str} {chunk_len}"
+    );
+
+    assert!(p1
+      .unknown
+      .iter()
+      .all(|token| matches!(token.instruction, Instruction::Other { .. })));
+  }
+}
+
+#[test]
+fn span_rejection_reasons() {
+  let input = b"mul(1234,5)mul(12;3)mul(1,2]mul(,3)don't(mul(2,2)don't()mul(2,2)do()mul(3,3)";
+  let spans = span_report(input, true)
+    .into_iter()
+    .map(|span| {
+      let text = std::str::from_utf8(&input[span.start..span.end]).unwrap();
+      (text, span.rejection)
+    })
+    .collect::<Vec<_>>();
+
+  assert_eq!(spans, [
+    ("mul(1234", Some(Rejection::TooManyDigits)),
+    ("mul(12;", Some(Rejection::MissingComma)),
+    ("mul(1,2]", Some(Rejection::MissingParen)),
+    ("mul(,", Some(Rejection::MissingDigits)),
+    ("don't(m", Some(Rejection::MissingParen)),
+    ("mul(2,2)", None),
+    ("don't()", None),
+    ("mul(2,2)", Some(Rejection::DisabledByDont)),
+    ("do()", None),
+    ("mul(3,3)", None),
+  ]);
+}
@@ -1,78 +1,685 @@
-const INPUT: &'static str = include_str!("../inputs/day5.txt");
+pub const INPUT: &'static str = include_str!("../inputs/day5.txt");
 
-use std::collections::{HashMap, HashSet};
+use std::{
+  cmp::Ordering,
+  fmt::{self, Display},
+};
 
-use pathfinding::directed::topological_sort::topological_sort;
+use crate::runner::answer;
 
-fn parse_input(input: &str) -> (Vec<(usize, usize)>, Vec<Vec<usize>>) {
-  let spl = input.split_once("\n\n").unwrap();
+/// Page numbers are all two digits, so the rules fit in a 100x100 bit matrix
+const PAGE_COUNT: usize = 100;
 
-  let deps: Vec<(usize, usize)> = spl
-    .0
-    .lines()
-    .map(|s| {
-      let (a, b) = s.split_once('|').unwrap();
-      (a.parse().unwrap(), b.parse().unwrap())
+fn pages_in_mask(mask: u128) -> impl Iterator<Item = u8> {
+  let mut mask = mask;
+  std::iter::from_fn(move || {
+    if mask == 0 {
+      return None;
+    }
+    let page = mask.trailing_zeros() as u8;
+    mask &= mask - 1;
+    Some(page)
+  })
+}
+
+fn update_mask(update: &[u8]) -> u128 { update.iter().fold(0, |mask, &page| mask | 1 << page) }
+
+/// The ordering rules as a bit matrix where bit `b` of `must_precede[a]` is set if there's a rule
+/// `a|b`, along with its transpose in `must_follow`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+  must_precede: [u128; PAGE_COUNT],
+  must_follow: [u128; PAGE_COUNT],
+}
+
+impl Default for Rules {
+  fn default() -> Self {
+    Rules {
+      must_precede: [0; PAGE_COUNT],
+      must_follow: [0; PAGE_COUNT],
+    }
+  }
+}
+
+/// How the rules restricted to the pages of one update order them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpdateOrder {
+  /// Every pair of pages is ordered by the rules, either directly or through other pages in the
+  /// update, so there's exactly one correct order
+  Total,
+  /// The rules don't decide the order of these pairs of pages, so there are several correct orders
+  Partial { unordered: Vec<(u8, u8)> },
+  /// The rules between the pages contain a cycle, so there's no correct order.  Each page in the
+  /// cycle must precede the next one, and the last must precede the first.
+  Cyclic { cycle: Vec<u8> },
+}
+
+impl Rules {
+  pub fn add(&mut self, before: u8, after: u8) {
+    assert!(
+      (before as usize) < PAGE_COUNT && (after as usize) < PAGE_COUNT,
+      "pages must be less than {PAGE_COUNT}; got {before}|{after}"
+    );
+    self.must_precede[before as usize] |= 1 << after;
+    self.must_follow[after as usize] |= 1 << before;
+  }
+
+  /// Bitset of every page that appears in at least one rule
+  pub fn pages(&self) -> u128 {
+    (0..PAGE_COUNT)
+      .filter(|&page| self.must_precede[page] | self.must_follow[page] != 0)
+      .fold(0, |mask, page| mask | 1 << page)
+  }
+
+  /// Returns true if there's a rule saying `a` must come before `b`
+  #[inline(always)]
+  pub fn must_precede(&self, a: u8, b: u8) -> bool { self.must_precede[a as usize] & (1 << b) != 0 }
+
+  /// Orders pages by the rules, treating pages without a rule between them as equal.
+  #[inline(always)]
+  pub fn compare(&self, a: u8, b: u8) -> Ordering {
+    if self.must_precede(a, b) {
+      Ordering::Less
+    } else if self.must_precede(b, a) {
+      Ordering::Greater
+    } else {
+      Ordering::Equal
+    }
+  }
+
+  /// Checks every pair of pages in `update`, not just adjacent ones, by keeping a bitset of the
+  /// pages seen so far and making sure none of them should have come after the current page.
+  pub fn is_ordered(&self, update: &[u8]) -> bool {
+    let mut seen = 0u128;
+    for &page in update {
+      if self.must_precede[page as usize] & seen != 0 {
+        return false;
+      }
+      seen |= 1 << page;
+    }
+    true
+  }
+
+  /// Returns true if there's a rule in exactly one direction between every pair of pages in
+  /// `pages` and those rules have no cycles, in which case `compare` is a total order over them and
+  /// can be used for sorting.
+  ///
+  /// With a rule between every pair, the rules are acyclic exactly when every page must precede a
+  /// different number of the others.
+  fn is_strict_total_order(&self, pages: u128) -> bool {
+    let mut seen_counts = 0u128;
+    for page in pages_in_mask(pages) {
+      let (after, before) = (
+        self.must_precede[page as usize] & pages,
+        self.must_follow[page as usize] & pages,
+      );
+      if after & before != 0 || (after | before | 1 << page) != pages {
+        return false;
+      }
+      seen_counts |= 1 << after.count_ones();
+    }
+    seen_counts.count_ones() == pages.count_ones()
+  }
+
+  /// Finds a cycle in the rules between the pages in `pages`, if there is one.
+  pub fn find_cycle(&self, pages: u128) -> Option<Vec<u8>> {
+    fn visit(rules: &Rules, pages: u128, page: u8, done: &mut u128, path: &mut Vec<u8>) -> bool {
+      path.push(page);
+      for next in pages_in_mask(rules.must_precede[page as usize] & pages & !*done) {
+        if let Some(start) = path.iter().position(|&p| p == next) {
+          path.drain(..start);
+          return true;
+        }
+        if visit(rules, pages, next, done, path) {
+          return true;
+        }
+      }
+      path.pop();
+      *done |= 1 << page;
+      false
+    }
+
+    let mut done = 0u128;
+    let mut path = Vec::new();
+    for page in pages_in_mask(pages) {
+      if done & (1 << page) == 0 && visit(self, pages, page, &mut done, &mut path) {
+        return Some(path);
+      }
+    }
+    None
+  }
+
+  /// Orders the pages in `pages` by repeatedly taking the smallest page that no remaining page must
+  /// precede.  Returns the cycle that got in the way if there isn't a valid order.
+  fn topological_order(&self, pages: u128) -> Result<Vec<u8>, Vec<u8>> {
+    let mut remaining = pages;
+    let mut order = Vec::with_capacity(pages.count_ones() as usize);
+    while remaining != 0 {
+      let Some(next) =
+        pages_in_mask(remaining).find(|&page| self.must_follow[page as usize] & remaining == 0)
+      else {
+        return Err(self.find_cycle(remaining).unwrap());
+      };
+      order.push(next);
+      remaining &= !(1 << next);
+    }
+    Ok(order)
+  }
+
+  /// Works out whether the rules put the pages of `update` in a single order.
+  pub fn update_order(&self, update: &[u8]) -> UpdateOrder {
+    let pages = update_mask(update);
+    if self.is_strict_total_order(pages) {
+      return UpdateOrder::Total;
+    }
+    let order = match self.topological_order(pages) {
+      Ok(order) => order,
+      Err(cycle) => return UpdateOrder::Cyclic { cycle },
+    };
+
+    // every page each page must precede, directly or through other pages in the update
+    let mut reachable = [0u128; PAGE_COUNT];
+    for &page in order.iter().rev() {
+      let direct = self.must_precede[page as usize] & pages;
+      reachable[page as usize] =
+        pages_in_mask(direct).fold(direct, |mask, next| mask | reachable[next as usize]);
+    }
+
+    // repeated pages would otherwise report the same pair more than once, in either order
+    let mut reported = [0u128; PAGE_COUNT];
+    let mut unordered = Vec::new();
+    for (i, &a) in update.iter().enumerate() {
+      for &b in &update[i + 1..] {
+        let ordered =
+          a == b || reachable[a as usize] & (1 << b) != 0 || reachable[b as usize] & (1 << a) != 0;
+        if !ordered && reported[a as usize] & (1 << b) == 0 {
+          reported[a as usize] |= 1 << b;
+          reported[b as usize] |= 1 << a;
+          unordered.push((a, b));
+        }
+      }
+    }
+    if unordered.is_empty() {
+      UpdateOrder::Total
+    } else {
+      UpdateOrder::Partial { unordered }
+    }
+  }
+
+  /// Reorders `update` so that it follows the rules.  If the rules only partially order its pages,
+  /// any order that follows them is used.  Returns the cycle in the rules if there's no valid
+  /// order.
+  pub fn fix(&self, update: &mut [u8]) -> Result<(), Vec<u8>> {
+    let pages = update_mask(update);
+    if self.is_strict_total_order(pages) {
+      update.sort_unstable_by(|&a, &b| self.compare(a, b));
+    } else {
+      // the mask only holds each page once, so repeated pages have to be put back as many times as
+      // they appeared
+      let mut counts = [0usize; PAGE_COUNT];
+      for &page in update.iter() {
+        counts[page as usize] += 1;
+      }
+      let mut slots = update.iter_mut();
+      for page in self.topological_order(pages)? {
+        for slot in slots.by_ref().take(counts[page as usize]) {
+          *slot = page;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Finds the page that ends up in the middle of `update` once it's fixed.
+  fn fixed_middle(&self, update: &mut [u8]) -> Result<u8, Vec<u8>> {
+    let middle_ix = update.len() / 2;
+    if self.is_strict_total_order(update_mask(update)) {
+      // only the middle page is needed, so there's no need to sort the whole update
+      let (_, &mut middle, _) =
+        update.select_nth_unstable_by(middle_ix, |&a, &b| self.compare(a, b));
+      Ok(middle)
+    } else {
+      self.fix(update)?;
+      Ok(update[middle_ix])
+    }
+  }
+}
+
+/// Problems found in a set of rules and updates
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Validation {
+  /// A cycle in the full set of rules.  This only matters if the pages of an update contain it.
+  pub cycle: Option<Vec<u8>>,
+  /// Pages that appear in updates but not in any rule
+  pub pages_without_rules: Vec<u8>,
+  /// How the rules order the pages of each update
+  pub updates: Vec<UpdateOrder>,
+}
+
+impl Validation {
+  pub fn new(rules: &Rules, updates: &[Vec<u8>]) -> Self {
+    let all_update_pages = updates
+      .iter()
+      .fold(0, |mask, update| mask | update_mask(update));
+    Validation {
+      cycle: rules.find_cycle(rules.pages()),
+      pages_without_rules: pages_in_mask(all_update_pages & !rules.pages()).collect(),
+      updates: updates
+        .iter()
+        .map(|update| rules.update_order(update))
+        .collect(),
+    }
+  }
+}
+
+fn write_chain(f: &mut fmt::Formatter<'_>, cycle: &[u8]) -> fmt::Result {
+  for page in cycle {
+    write!(f, "{page} -> ")?;
+  }
+  write!(f, "{}", cycle[0])
+}
+
+impl Display for Validation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.cycle {
+      Some(cycle) => {
+        write!(f, "rules contain a cycle: ")?;
+        write_chain(f, cycle)?;
+        writeln!(f)?;
+      },
+      None => writeln!(f, "rules are acyclic")?,
+    }
+    if !self.pages_without_rules.is_empty() {
+      writeln!(f, "pages without rules: {:?}", self.pages_without_rules)?;
+    }
+
+    for (ix, order) in self.updates.iter().enumerate() {
+      write!(f, "update {ix}: ")?;
+      match order {
+        UpdateOrder::Total => writeln!(f, "total order")?,
+        UpdateOrder::Partial { unordered } =>
+          writeln!(f, "partial order; no rule orders {unordered:?}")?,
+        UpdateOrder::Cyclic { cycle } => {
+          write!(f, "cyclic: ")?;
+          write_chain(f, cycle)?;
+          writeln!(f)?;
+        },
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Checks the rules and updates in `input` for cycles, missing rules, and updates without a single
+/// correct order.
+pub fn validate(input: &str) -> Validation {
+  let (rules, updates) = parse_input(input);
+  Validation::new(&rules, &updates)
+}
+
+/// A pair of pages in an update that are in the wrong order: the rule `later_page|earlier_page`
+/// is broken because `earlier_page` comes first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Violation {
+  pub earlier_ix: usize,
+  pub later_ix: usize,
+  pub earlier_page: u8,
+  pub later_page: u8,
+}
+
+/// Takes `page` out of the update at index `from` and inserts it so that it ends up at index `to`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+  pub page: u8,
+  pub from: usize,
+  pub to: usize,
+}
+
+impl Move {
+  pub fn apply(&self, update: &mut Vec<u8>) {
+    let page = update.remove(self.from);
+    debug_assert_eq!(page, self.page);
+    update.insert(self.to, page);
+  }
+}
+
+/// Explains what's wrong with an update and how to fix it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdateReport {
+  pub update: Vec<u8>,
+  /// Every pair of pages that breaks a rule, ordered by their positions
+  pub violations: Vec<Violation>,
+  pub corrected: Vec<u8>,
+  /// The fewest moves that turn `update` into `corrected` when applied in order
+  pub moves: Vec<Move>,
+}
+
+impl UpdateReport {
+  /// Returns the cycle in the rules if the update can't be corrected.
+  pub fn new(rules: &Rules, update: &[u8]) -> Result<Self, Vec<u8>> {
+    let mut violations = Vec::new();
+    for (earlier_ix, &earlier_page) in update.iter().enumerate() {
+      for (later_ix, &later_page) in update.iter().enumerate().skip(earlier_ix + 1) {
+        if rules.must_precede(later_page, earlier_page) {
+          violations.push(Violation {
+            earlier_ix,
+            later_ix,
+            earlier_page,
+            later_page,
+          });
+        }
+      }
+    }
+
+    let mut corrected = update.to_vec();
+    rules.fix(&mut corrected)?;
+    let moves = Self::minimal_moves(update, &corrected);
+
+    Ok(UpdateReport {
+      update: update.to_vec(),
+      violations,
+      corrected,
+      moves,
     })
-    .collect();
+  }
+
+  /// Every page that isn't moved keeps its place relative to the others, so the pages that stay put
+  /// have to already be in corrected order.  Keeping the longest such subsequence gives the fewest
+  /// moves, and each other page is then moved to just after the page that precedes it in the
+  /// corrected order, in corrected order.
+  fn minimal_moves(update: &[u8], corrected: &[u8]) -> Vec<Move> {
+    // where each page ends up in `corrected`, with repeated pages keeping their order so that every
+    // page gets a different index
+    let mut placed = vec![false; corrected.len()];
+    let targets = update
+      .iter()
+      .map(|&page| {
+        let target = (0..corrected.len())
+          .find(|&ix| !placed[ix] && corrected[ix] == page)
+          .unwrap();
+        placed[target] = true;
+        target
+      })
+      .collect::<Vec<_>>();
 
-  let pages: Vec<Vec<usize>> = spl
-    .1
+    // longest increasing subsequence of target indices by patience sorting, where `tails[len]` is
+    // the index in `update` of the smallest tail of an increasing subsequence of length `len + 1`
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev = vec![usize::MAX; update.len()];
+    for (ix, &target) in targets.iter().enumerate() {
+      let len = tails.partition_point(|&tail_ix| targets[tail_ix] < target);
+      if len > 0 {
+        prev[ix] = tails[len - 1];
+      }
+      if len == tails.len() {
+        tails.push(ix);
+      } else {
+        tails[len] = ix;
+      }
+    }
+    let mut kept = vec![false; corrected.len()];
+    let mut ix = tails.last().copied().unwrap_or(usize::MAX);
+    while ix != usize::MAX {
+      kept[targets[ix]] = true;
+      ix = prev[ix];
+    }
+
+    // pages are tracked by their target index so that repeated ones can be told apart
+    let mut current = targets;
+    let mut moves = Vec::new();
+    for target in 0..corrected.len() {
+      if kept[target] {
+        continue;
+      }
+      let from = current.iter().position(|&t| t == target).unwrap();
+      current.remove(from);
+      let to = match target {
+        0 => 0,
+        _ => current.iter().position(|&t| t == target - 1).unwrap() + 1,
+      };
+      current.insert(to, target);
+      moves.push(Move {
+        page: corrected[target],
+        from,
+        to,
+      });
+    }
+    debug_assert!(current.iter().enumerate().all(|(ix, &target)| ix == target));
+
+    moves
+  }
+}
+
+impl Display for UpdateReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "update {:?}", self.update)?;
+    for v in &self.violations {
+      writeln!(
+        f,
+        "  breaks {}|{}: page {} at {} comes before page {} at {}",
+        v.later_page, v.earlier_page, v.earlier_page, v.earlier_ix, v.later_page, v.later_ix
+      )?;
+    }
+    writeln!(f, "  corrected: {:?}", self.corrected)?;
+    for m in &self.moves {
+      writeln!(f, "  move page {} from {} to {}", m.page, m.from, m.to)?;
+    }
+    Ok(())
+  }
+}
+
+/// Explains every incorrectly ordered update in `input` along with its index, or gives the cycle
+/// that stops it from being corrected.
+pub fn violation_reports(input: &str) -> Vec<(usize, Result<UpdateReport, Vec<u8>>)> {
+  let (rules, updates) = parse_input(input);
+  updates
+    .iter()
+    .enumerate()
+    .filter(|(_, update)| !rules.is_ordered(update))
+    .map(|(ix, update)| (ix, UpdateReport::new(&rules, update)))
+    .collect()
+}
+
+fn parse_input(input: &str) -> (Rules, Vec<Vec<u8>>) {
+  let (rules_str, updates_str) = input.split_once("\n\n").unwrap();
+
+  let mut rules = Rules::default();
+  for line in rules_str.lines() {
+    let (a, b) = line.split_once('|').unwrap();
+    rules.add(a.parse().unwrap(), b.parse().unwrap());
+  }
+
+  let updates = updates_str
     .lines()
     .map(|s| s.split(',').map(|s| s.parse().unwrap()).collect())
     .collect();
 
-  (deps, pages)
+  (rules, updates)
+}
+
+pub fn part1(input: &str) -> usize {
+  let (rules, updates) = parse_input(input);
+  updates
+    .iter()
+    .filter(|update| rules.is_ordered(update))
+    .map(|update| update[update.len() / 2] as usize)
+    .sum()
+}
+
+pub fn part2(input: &str) -> usize {
+  let (rules, mut updates) = parse_input(input);
+  updates
+    .iter_mut()
+    .filter(|update| !rules.is_ordered(update))
+    .map(|update| match rules.fixed_middle(update) {
+      Ok(middle) => middle as usize,
+      Err(cycle) => panic!("rules for update {update:?} contain a cycle: {cycle:?}"),
+    })
+    .sum()
 }
 
 pub fn solve() {
-  let (deps, pages) = parse_input(INPUT);
+  answer(1, part1(INPUT));
+  answer(2, part2(INPUT));
+}
+
+pub fn run(input: &str) -> impl Display { part2(input) }
+
+#[cfg(test)]
+const EXAMPLE: &str = "\
+47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
 
-  let roots: HashSet<usize> = deps.iter().flat_map(|(a, b)| [*a, *b]).collect();
-  let mut successors_by_key: HashMap<usize, Vec<usize>> = HashMap::default();
-  for (a, b) in &deps {
-    successors_by_key.entry(*a).or_default().push(*b);
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47
+";
+
+#[test]
+fn example() {
+  assert_eq!(part1(EXAMPLE), 143);
+  assert_eq!(part2(EXAMPLE), 123);
+
+  let (rules, mut updates) = parse_input(EXAMPLE);
+  rules.fix(&mut updates[5]).unwrap();
+  assert_eq!(updates[5], [97, 75, 47, 29, 13]);
+}
+
+#[test]
+fn validation() {
+  // the rules as a whole have the cycle 1 -> 2 -> 3 -> 1, but no update contains all of its pages
+  let input = "1|2\n2|3\n3|1\n2|4\n4|5\n\n2,1,4\n3,2\n1,4,6\n1,2,3\n";
+  let validation = validate(input);
+
+  let cycle = validation.cycle.clone().unwrap();
+  assert_eq!(cycle.len(), 3);
+  let (rules, updates) = parse_input(input);
+  for (ix, &page) in cycle.iter().enumerate() {
+    assert!(rules.must_precede(page, cycle[(ix + 1) % cycle.len()]));
   }
 
-  let (sorted_pages, unsorted_pages): (Vec<_>, Vec<_>) = pages
-    .iter()
-    .map(|pages| {
-      let sorted = topological_sort(
-        &roots
-          .iter()
-          .filter(|r| pages.contains(*r))
-          .copied()
-          .collect::<Vec<_>>(),
-        |n| {
-          successors_by_key[n]
-            .iter()
-            .copied()
-            .filter(|s| pages.contains(s))
-        },
-      )
-      .unwrap();
+  assert_eq!(validation.pages_without_rules, [6]);
+  // 1 -> 2 -> 4 orders the first update even without a 1|4 rule
+  assert_eq!(validation.updates[0], UpdateOrder::Total);
+  assert_eq!(validation.updates[1], UpdateOrder::Total);
+  assert_eq!(validation.updates[2], UpdateOrder::Partial {
+    unordered: vec![(1, 4), (1, 6), (4, 6)]
+  });
+  assert!(matches!(validation.updates[3], UpdateOrder::Cyclic { .. }));
 
-      (sorted, pages)
-    })
-    .partition(|(sorted, pages)| {
-      pages.is_sorted_by_key(|p| sorted.iter().position(|an| an == p).unwrap())
-    });
+  let mut fixed = updates[0].clone();
+  rules.fix(&mut fixed).unwrap();
+  assert_eq!(fixed, [1, 2, 4]);
+  assert!(rules.fix(&mut updates[3].clone()).is_err());
+  // repeated pages are kept rather than collapsed into one, and are never out of order with
+  // themselves
+  assert_eq!(rules.update_order(&[1, 2, 4, 1]), UpdateOrder::Total);
+  assert_eq!(rules.update_order(&[1, 6, 4, 1, 6]), UpdateOrder::Partial {
+    unordered: vec![(1, 6), (1, 4), (6, 4)]
+  });
+  let mut repeated = vec![4, 1, 6, 4, 1];
+  rules.fix(&mut repeated).unwrap();
+  assert_eq!(repeated, [1, 1, 4, 4, 6]);
+  let report = UpdateReport::new(&rules, &[4, 1, 6, 4, 1]).unwrap();
+  assert_eq!(report.corrected, [1, 1, 4, 4, 6]);
+  let mut moved = report.update.clone();
+  for m in &report.moves {
+    m.apply(&mut moved);
+  }
+  assert_eq!(moved, report.corrected);
 
-  let sum = sorted_pages
-    .iter()
-    .map(|(_sorted, pages)| {
-      let middle_page_ix = pages.len() / 2;
-      pages[middle_page_ix]
-    })
-    .sum::<usize>();
+  let input = input.rsplit_once("1,2,3\n").unwrap().0;
+  assert_eq!(part1(input), 4);
+  assert_eq!(part2(input), 2 + 3);
+}
 
-  println!("Part 1: {sum}");
+#[test]
+fn violation_report() {
+  let reports = violation_reports(EXAMPLE);
+  assert_eq!(reports.iter().map(|(ix, _)| *ix).collect::<Vec<_>>(), [
+    3, 4, 5
+  ]);
 
-  let mut sum = 0;
-  for (sorted, pages) in unsorted_pages {
-    let middle_page_ix = pages.len() / 2;
-    sum += sorted[middle_page_ix];
+  let report = reports[2].1.as_ref().unwrap();
+  assert_eq!(
+    report
+      .violations
+      .iter()
+      .map(|v| (v.earlier_ix, v.later_ix))
+      .collect::<Vec<_>>(),
+    [(1, 2), (1, 3), (1, 4), (3, 4)]
+  );
+  assert_eq!(report.violations[0], Violation {
+    earlier_ix: 1,
+    later_ix: 2,
+    earlier_page: 13,
+    later_page: 75,
+  });
+  assert_eq!(report.corrected, [97, 75, 47, 29, 13]);
+  assert_eq!(report.moves.len(), 2);
+
+  // the moves always give the corrected order, and the pages they leave alone are the longest run
+  // of pages that's already in corrected order
+  let mut state = 0x9e3779b97f4a7c15u64;
+  let mut rand = |n: usize| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state as usize % n
+  };
+  let mut rules = Rules::default();
+  for a in 10..30 {
+    for b in a + 1..30 {
+      rules.add(a, b);
+    }
   }
+  for _ in 0..500 {
+    let mut update = (10..30).collect::<Vec<u8>>();
+    for i in (1..update.len()).rev() {
+      update.swap(i, rand(i + 1));
+    }
+    update.truncate(rand(update.len()) + 1);
+
+    let report = UpdateReport::new(&rules, &update).unwrap();
+    let mut moved = update.clone();
+    for m in &report.moves {
+      m.apply(&mut moved);
+    }
+    assert_eq!(moved, report.corrected);
 
-  println!("Part 2: {sum}");
+    let mut longest = vec![1; update.len()];
+    for i in 0..update.len() {
+      for j in 0..i {
+        if update[j] < update[i] {
+          longest[i] = longest[i].max(longest[j] + 1);
+        }
+      }
+    }
+    assert_eq!(
+      report.moves.len(),
+      update.len() - longest.iter().max().unwrap()
+    );
+  }
 }
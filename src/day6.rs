@@ -2,202 +2,699 @@
 
 pub const INPUT_BYTES: &'static [u8] = include_bytes!("../inputs/day6.txt");
 
-use std::{
-  fmt::Display,
-  sync::atomic::{AtomicUsize, Ordering},
-};
+use std::{fmt::Display, io};
 
-use fxhash::FxHashSet;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-const GRID_SIZE: usize = 130;
-const GRID_SIZE_PLUS_NEWLINE: usize = GRID_SIZE + 1;
+use crate::{
+  runner::answer,
+  viz::{Frame, Player, Rgb},
+};
 
-static mut GUARD_POS: (usize, usize) = (0, 0);
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Dir {
+  Up,
+  Left,
+  Down,
+  Right,
+}
 
-type Grid = [bool; GRID_SIZE * GRID_SIZE];
+impl Dir {
+  fn from_guard(c: u8) -> Option<Dir> {
+    match c {
+      b'^' => Some(Dir::Up),
+      b'<' => Some(Dir::Left),
+      b'v' => Some(Dir::Down),
+      b'>' => Some(Dir::Right),
+      _ => None,
+    }
+  }
+
+  fn guard_char(self) -> char {
+    match self {
+      Dir::Up => '^',
+      Dir::Left => '<',
+      Dir::Down => 'v',
+      Dir::Right => '>',
+    }
+  }
 
-static mut GRID: Grid = [false; GRID_SIZE * GRID_SIZE];
+  pub fn name(self) -> &'static str {
+    match self {
+      Dir::Up => "up",
+      Dir::Left => "left",
+      Dir::Down => "down",
+      Dir::Right => "right",
+    }
+  }
 
-fn grid() -> &'static mut Grid { unsafe { &mut GRID } }
+  fn turn_right(self) -> Dir {
+    match self {
+      Dir::Up => Dir::Right,
+      Dir::Left => Dir::Up,
+      Dir::Down => Dir::Left,
+      Dir::Right => Dir::Down,
+    }
+  }
+}
 
-pub fn parse_input(input: &[u8]) -> ((usize, usize), &'static Grid) {
-  for (y, row) in input.array_chunks::<GRID_SIZE_PLUS_NEWLINE>().enumerate() {
-    for (x, &c) in row[..GRID_SIZE].iter().enumerate() {
-      if c == '^' as u8 {
-        unsafe {
-          GUARD_POS = (x, y);
-        }
-        continue;
+/// The lab map, with its size taken from the input
+#[derive(Clone)]
+pub struct Map {
+  width: usize,
+  height: usize,
+  obstacles: Vec<bool>,
+  guard_pos: (usize, usize),
+  guard_dir: Dir,
+}
+
+impl Map {
+  fn cell_count(&self) -> usize { self.width * self.height }
+
+  #[inline(always)]
+  fn is_obstacle(&self, x: usize, y: usize) -> bool {
+    unsafe { *self.obstacles.get_unchecked(y * self.width + x) }
+  }
+
+  pub fn width(&self) -> usize { self.width }
+
+  pub fn height(&self) -> usize { self.height }
+
+  /// Follows the guard step by step, with an extra obstacle at `obstruction` if given, until it
+  /// either walks off the map or gets stuck in a loop.
+  pub fn trace(&self, obstruction: Option<(usize, usize)>) -> Trace {
+    let mut map = self.clone();
+    if let Some((x, y)) = obstruction {
+      assert!(
+        x < self.width && y < self.height,
+        "obstruction {obstruction:?} is off the map"
+      );
+      map.obstacles[y * self.width + x] = true;
+    }
+
+    // index into `states` of each state the first time it's reached
+    let mut state_ix = vec![u32::MAX; 4 * self.cell_count()];
+    let state_ix_of = |pos: (isize, isize), dir: Dir| {
+      dir as usize * self.cell_count() + pos.1 as usize * self.width + pos.0 as usize
+    };
+
+    let mut pos = (self.guard_pos.0 as isize, self.guard_pos.1 as isize);
+    let mut dir = self.guard_dir;
+    let mut states = vec![GuardState {
+      pos: self.guard_pos,
+      dir,
+    }];
+    let mut turns = Vec::new();
+    state_ix[state_ix_of(pos, dir)] = 0;
+
+    let end = loop {
+      let Some((next_pos, next_dir)) = step_guard(&map, pos, dir) else {
+        break TraceEnd::LeftMap;
+      };
+      let seen_ix = &mut state_ix[state_ix_of(next_pos, next_dir)];
+      if *seen_ix != u32::MAX {
+        let start = *seen_ix as usize;
+        break TraceEnd::Loop {
+          start,
+          len: states.len() - start,
+        };
+      }
+      *seen_ix = states.len() as u32;
+
+      if next_dir != dir {
+        turns.push(states.len());
+      }
+      (pos, dir) = (next_pos, next_dir);
+      states.push(GuardState {
+        pos: (pos.0 as usize, pos.1 as usize),
+        dir,
+      });
+    };
+
+    Trace {
+      obstruction,
+      states,
+      turns,
+      end,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GuardState {
+  pub pos: (usize, usize),
+  pub dir: Dir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEnd {
+  LeftMap,
+  /// The guard is stuck repeating `states[start..start + len]` forever
+  Loop {
+    start: usize,
+    len: usize,
+  },
+}
+
+/// Every state the guard goes through from the start of its patrol, without repeating any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+  pub obstruction: Option<(usize, usize)>,
+  pub states: Vec<GuardState>,
+  /// Indices into `states` of the states right after each turn
+  pub turns: Vec<usize>,
+  pub end: TraceEnd,
+}
+
+impl Trace {
+  /// Draws the path like the puzzle text does, with `|` and `-` for vertical and horizontal moves,
+  /// `+` where the path goes both ways, `O` for the extra obstruction, and the guard where it
+  /// started.
+  pub fn render(&self, map: &Map) -> String {
+    const VERTICAL: u8 = 1;
+    const HORIZONTAL: u8 = 2;
+
+    let mut directions = vec![0u8; map.cell_count()];
+    for state in &self.states {
+      directions[state.pos.1 * map.width + state.pos.0] |= match state.dir {
+        Dir::Up | Dir::Down => VERTICAL,
+        Dir::Left | Dir::Right => HORIZONTAL,
+      };
+    }
+
+    let mut out = String::with_capacity((map.width + 1) * map.height);
+    for y in 0..map.height {
+      for x in 0..map.width {
+        out.push(if Some((x, y)) == self.obstruction {
+          'O'
+        } else if map.is_obstacle(x, y) {
+          '#'
+        } else if (x, y) == map.guard_pos {
+          map.guard_dir.guard_char()
+        } else {
+          match directions[y * map.width + x] {
+            VERTICAL => '|',
+            HORIZONTAL => '-',
+            0 => '.',
+            _ => '+',
+          }
+        });
       }
+      out.push('\n');
+    }
+    out
+  }
+
+  /// Writes the trace as JSON lines, one object per state followed by one describing how it ended.
+  pub fn write_json(&self, mut out: impl io::Write) -> io::Result<()> {
+    let mut turns = self.turns.iter().peekable();
+    for (step, state) in self.states.iter().enumerate() {
+      let turn = turns.next_if_eq(&&step).is_some();
+      writeln!(
+        out,
+        r#"{{"step":{step},"x":{},"y":{},"dir":"{}","turn":{turn}}}"#,
+        state.pos.0,
+        state.pos.1,
+        state.dir.name(),
+      )?;
+    }
+    match self.end {
+      TraceEnd::LeftMap => writeln!(out, r#"{{"end":"left_map"}}"#),
+      TraceEnd::Loop { start, len } => writeln!(
+        out,
+        r#"{{"end":"loop","cycle_start":{start},"cycle_len":{len}}}"#
+      ),
+    }
+  }
+}
+
+/// Parses a map of `.` and `#` rows with a single guard facing whichever way `^`, `>`, `v` or `<`
+/// points.  Panics if the rows have different lengths or there's no guard.
+pub fn parse_input(input: &[u8]) -> Map {
+  let mut rows = input
+    .split(|&c| c == '\n' as u8)
+    .map(|row| row.strip_suffix(b"\r").unwrap_or(row))
+    .collect::<Vec<_>>();
+  if rows.last().is_some_and(|row| row.is_empty()) {
+    rows.pop();
+  }
+
+  let width = rows.first().map(|row| row.len()).unwrap_or(0);
+  let height = rows.len();
+  let mut obstacles = vec![false; width * height];
+  let mut guard = None;
+  for (y, row) in rows.iter().enumerate() {
+    assert_eq!(
+      row.len(),
+      width,
+      "row {y} has a different length from the first row"
+    );
+    for (x, &c) in row.iter().enumerate() {
       if c == '#' as u8 {
-        unsafe { *grid().get_unchecked_mut(y * GRID_SIZE + x) = true };
+        obstacles[y * width + x] = true;
+      } else if let Some(dir) = Dir::from_guard(c) {
+        guard = Some(((x, y), dir));
       }
     }
   }
 
-  (unsafe { GUARD_POS }, grid())
+  let (guard_pos, guard_dir) = guard.expect("no guard on the map");
+  Map {
+    width,
+    height,
+    obstacles,
+    guard_pos,
+    guard_dir,
+  }
 }
 
-#[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum Dir {
-  Up,
-  Left,
-  Down,
-  Right,
+const OUT_OF_BOUNDS: u32 = u32::MAX;
+
+/// For every cell and direction, the cell where the guard stops when walking in that direction
+/// from it because the next cell is an obstacle, or `OUT_OF_BOUNDS` if it walks off the map first.
+/// This lets the simulation jump straight from one turn to the next.
+struct JumpTable {
+  width: usize,
+  cell_count: usize,
+  next_stop: Vec<u32>,
+}
+
+impl JumpTable {
+  fn new(map: &Map) -> Self {
+    let (width, height, cell_count) = (map.width, map.height, map.cell_count());
+    let mut next_stop = vec![OUT_OF_BOUNDS; 4 * cell_count];
+    let ix = |x: usize, y: usize| (y * width + x) as u32;
+
+    // sweep each row and column in the opposite direction to the one being walked, keeping track of
+    // the cell right before the last obstacle passed.  An obstacle on the last edge swept leaves
+    // nothing after it, so it doesn't matter that its stop gets clamped.
+    for x in 0..width {
+      let mut stop = OUT_OF_BOUNDS;
+      for y in 0..height {
+        if map.is_obstacle(x, y) {
+          stop = ix(x, y + 1);
+        } else {
+          next_stop[Dir::Up as usize * cell_count + ix(x, y) as usize] = stop;
+        }
+      }
+
+      let mut stop = OUT_OF_BOUNDS;
+      for y in (0..height).rev() {
+        if map.is_obstacle(x, y) {
+          stop = ix(x, y.saturating_sub(1));
+        } else {
+          next_stop[Dir::Down as usize * cell_count + ix(x, y) as usize] = stop;
+        }
+      }
+    }
+
+    for y in 0..height {
+      let mut stop = OUT_OF_BOUNDS;
+      for x in 0..width {
+        if map.is_obstacle(x, y) {
+          stop = ix(x + 1, y);
+        } else {
+          next_stop[Dir::Left as usize * cell_count + ix(x, y) as usize] = stop;
+        }
+      }
+
+      let mut stop = OUT_OF_BOUNDS;
+      for x in (0..width).rev() {
+        if map.is_obstacle(x, y) {
+          stop = ix(x.saturating_sub(1), y);
+        } else {
+          next_stop[Dir::Right as usize * cell_count + ix(x, y) as usize] = stop;
+        }
+      }
+    }
+
+    JumpTable {
+      width,
+      cell_count,
+      next_stop,
+    }
+  }
+
+  /// Finds where the guard stops when walking from `pos` in `dir` with an extra obstacle at
+  /// `obstruction`.  The extra obstacle can only cut short jumps along its own row or column, so
+  /// rather than patching the table it's checked against the jump being made.
+  #[inline(always)]
+  fn jump(&self, pos: (usize, usize), dir: Dir, obstruction: (usize, usize)) -> u32 {
+    let stop = unsafe {
+      *self
+        .next_stop
+        .get_unchecked(dir as usize * self.cell_count + pos.1 * self.width + pos.0)
+    };
+    let (stop_x, stop_y) = ((stop as usize) % self.width, (stop as usize) / self.width);
+    let (ox, oy) = obstruction;
+    let ix = |x: usize, y: usize| (y * self.width + x) as u32;
+
+    match dir {
+      Dir::Up if ox == pos.0 && oy < pos.1 && (stop == OUT_OF_BOUNDS || oy >= stop_y) =>
+        ix(ox, oy + 1),
+      Dir::Down if ox == pos.0 && oy > pos.1 && (stop == OUT_OF_BOUNDS || oy <= stop_y) =>
+        ix(ox, oy - 1),
+      Dir::Left if oy == pos.1 && ox < pos.0 && (stop == OUT_OF_BOUNDS || ox >= stop_x) =>
+        ix(ox + 1, oy),
+      Dir::Right if oy == pos.1 && ox > pos.0 && (stop == OUT_OF_BOUNDS || ox <= stop_x) =>
+        ix(ox - 1, oy),
+      _ => stop,
+    }
+  }
 }
 
+/// Returns true if the guard gets stuck in a loop when starting at `guard_pos` facing `dir` with an
+/// extra obstacle at `obstruction`.
+///
+/// Only the states right after each turn are tracked, marking them in `visited` with `stamp` so
+/// that the same buffer can be reused for every simulation without clearing it.
 fn sim_part2(
-  obstruction_pos: (usize, usize),
-  grid: &Grid,
+  table: &JumpTable,
+  obstruction: (usize, usize),
   mut guard_pos: (usize, usize),
   mut dir: Dir,
+  visited: &mut [u32],
+  stamp: u32,
 ) -> bool {
-  let mut visited_positions_directions: Vec<bool> = Vec::new();
-  visited_positions_directions.resize(GRID_SIZE * GRID_SIZE * 4, false);
-
-  // returns `false` if marking a spot that's already been marked
-  let mut insert_visited_pos_dir = |guard_pos: (usize, usize), dir: Dir| -> bool {
-    let dir_multiplier = match dir {
-      Dir::Up => 0,
-      Dir::Left => 1,
-      Dir::Down => 2,
-      Dir::Right => 3,
-    };
-    let ix = dir_multiplier * (GRID_SIZE * GRID_SIZE) + guard_pos.1 * GRID_SIZE + guard_pos.0;
-    if unsafe { *visited_positions_directions.get_unchecked(ix) } {
+  loop {
+    let stop = table.jump(guard_pos, dir, obstruction);
+    if stop == OUT_OF_BOUNDS {
       return false;
     }
 
-    unsafe {
-      *visited_positions_directions.get_unchecked_mut(ix) = true;
+    dir = dir.turn_right();
+    guard_pos = ((stop as usize) % table.width, (stop as usize) / table.width);
+    let visited_ix = dir as usize * table.cell_count + stop as usize;
+    let seen = unsafe { visited.get_unchecked_mut(visited_ix) };
+    if *seen == stamp {
+      return true;
     }
-    true
+    *seen = stamp;
+  }
+}
+
+/// The guard's patrol never leaves the map, so neither part has an answer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PatrolLoops;
+
+/// Walks the guard's patrol one step at a time, calling `on_step` with the state before and after
+/// each step, until it walks off the map.  Returns `Err(PatrolLoops)` if it never does.
+///
+/// A loop has to turn somewhere, so only the states right after each turn are remembered.
+fn patrol(
+  map: &Map,
+  mut on_step: impl FnMut((isize, isize), Dir, (isize, isize), Dir),
+) -> Result<(), PatrolLoops> {
+  let mut turned = vec![false; 4 * map.cell_count()];
+  let mut pos = (map.guard_pos.0 as isize, map.guard_pos.1 as isize);
+  let mut dir = map.guard_dir;
+  while let Some((next_pos, next_dir)) = step_guard(map, pos, dir) {
+    if next_dir != dir {
+      let ix = next_dir as usize * map.cell_count()
+        + next_pos.1 as usize * map.width
+        + next_pos.0 as usize;
+      if turned[ix] {
+        return Err(PatrolLoops);
+      }
+      turned[ix] = true;
+    }
+    on_step(pos, dir, next_pos, next_dir);
+    (pos, dir) = (next_pos, next_dir);
+  }
+  Ok(())
+}
+
+/// Counts the distinct cells the guard visits before leaving the map.
+pub fn visited_cells(map: &Map) -> Result<usize, PatrolLoops> {
+  let mut visited = vec![0u64; map.cell_count().div_ceil(64)];
+  let mut mark_visited = |pos: (isize, isize)| {
+    let ix = pos.1 as usize * map.width + pos.0 as usize;
+    visited[ix / 64] |= 1 << (ix % 64);
   };
 
-  insert_visited_pos_dir(guard_pos, dir);
+  mark_visited((map.guard_pos.0 as isize, map.guard_pos.1 as isize));
+  patrol(map, |_, _, next_pos, _| mark_visited(next_pos))?;
 
-  'outer: loop {
-    loop {
-      let next_pos = match dir {
-        Dir::Up => {
-          if guard_pos.1 == 0 {
-            break 'outer;
-          }
-          (guard_pos.0, guard_pos.1 - 1)
-        },
-        Dir::Left => {
-          if guard_pos.0 == 0 {
-            break 'outer;
-          }
-          (guard_pos.0 - 1, guard_pos.1)
-        },
-        Dir::Down => {
-          if guard_pos.1 >= (GRID_SIZE - 1) {
-            break 'outer;
-          }
-          (guard_pos.0, guard_pos.1 + 1)
-        },
-        Dir::Right => {
-          if guard_pos.0 >= (GRID_SIZE - 1) {
-            break 'outer;
-          }
-          (guard_pos.0 + 1, guard_pos.1)
-        },
-      };
+  Ok(visited.iter().map(|bits| bits.count_ones() as usize).sum())
+}
 
-      if next_pos == obstruction_pos
-        || unsafe { *grid.get_unchecked(next_pos.1 * GRID_SIZE + next_pos.0) }
-      {
-        break;
-      }
+/// Counts the cells where a single extra obstacle would get the guard stuck in a loop.
+pub fn loop_obstructions(map: &Map) -> Result<usize, PatrolLoops> {
+  let table = JumpTable::new(map);
 
-      guard_pos = next_pos;
-      if !insert_visited_pos_dir(guard_pos, dir) {
-        return true;
-      }
+  // an obstruction can only change anything if it's on the guard's original path, and everything
+  // up to the first time the guard would walk into it plays out the same as without it.  So each
+  // cell on the path gets tried once, starting from the state just before the guard first reaches
+  // it.
+  let mut candidates = Vec::new();
+  let mut seen = vec![false; map.cell_count()];
+  seen[map.guard_pos.1 * map.width + map.guard_pos.0] = true;
+  patrol(map, |pos, dir, next_pos, _| {
+    let next_ix = next_pos.1 as usize * map.width + next_pos.0 as usize;
+    if next_pos != pos && !seen[next_ix] {
+      seen[next_ix] = true;
+      let obstruction = (next_pos.0 as usize, next_pos.1 as usize);
+      candidates.push((obstruction, (pos.0 as usize, pos.1 as usize), dir));
     }
+  })?;
 
-    dir = match dir {
-      Dir::Up => Dir::Right,
-      Dir::Left => Dir::Up,
-      Dir::Down => Dir::Left,
-      Dir::Right => Dir::Down,
-    };
-    continue;
-  }
+  Ok(
+    candidates
+      .into_par_iter()
+      .map_init(
+        || (vec![0u32; 4 * table.cell_count], 0u32),
+        |(visited, stamp), (obstruction, start_pos, start_dir)| {
+          *stamp += 1;
+          sim_part2(&table, obstruction, start_pos, start_dir, visited, *stamp)
+        },
+      )
+      .filter(|&did_loop| did_loop)
+      .count(),
+  )
+}
 
-  false
+pub fn part1(input: &[u8]) -> usize {
+  visited_cells(&parse_input(input)).expect("the guard never leaves the map")
 }
 
 pub fn part2(input: &[u8]) -> usize {
-  let (guard_pos, grid) = parse_input(input);
-
-  let loop_count = AtomicUsize::new(0);
-  let xs = 0..GRID_SIZE;
-  let ys = 0..GRID_SIZE;
-  xs.into_par_iter().for_each(|x| {
-    for y in ys.clone() {
-      let obstruction_pos = (x, y);
-      if obstruction_pos == guard_pos {
-        continue;
-      }
-      if unsafe { *grid.get_unchecked(obstruction_pos.1 * GRID_SIZE + obstruction_pos.0) } {
-        continue;
-      }
+  loop_obstructions(&parse_input(input)).expect("the guard never leaves the map")
+}
 
-      let did_loop = sim_part2(obstruction_pos, &grid, guard_pos, Dir::Up);
-      if did_loop {
-        loop_count.fetch_add(1, Ordering::Relaxed);
-      }
+/// Advances the guard by one step, turning instead of moving if there's an obstacle in the way.
+/// Returns `None` once the guard walks off the map.
+fn step_guard(map: &Map, guard_pos: (isize, isize), dir: Dir) -> Option<((isize, isize), Dir)> {
+  let next_pos = match dir {
+    Dir::Up => (guard_pos.0, guard_pos.1 - 1),
+    Dir::Left => (guard_pos.0 - 1, guard_pos.1),
+    Dir::Down => (guard_pos.0, guard_pos.1 + 1),
+    Dir::Right => (guard_pos.0 + 1, guard_pos.1),
+  };
+  if next_pos.0 < 0
+    || next_pos.0 >= map.width as isize
+    || next_pos.1 < 0
+    || next_pos.1 >= map.height as isize
+  {
+    return None;
+  }
+
+  if map.is_obstacle(next_pos.0 as usize, next_pos.1 as usize) {
+    Some((guard_pos, dir.turn_right()))
+  } else {
+    Some((next_pos, dir))
+  }
+}
+
+pub fn solve() {
+  answer(1, part1(INPUT_BYTES));
+  answer(2, part2(INPUT_BYTES));
+}
+
+pub fn run(input: &[u8]) -> impl Display { part2(input) }
+
+/// Plays back the guard's patrol from part 1 in the terminal.
+pub fn visualize() {
+  let map = parse_input(INPUT_BYTES);
+  let mut guard_pos = (map.guard_pos.0 as isize, map.guard_pos.1 as isize);
+  let mut dir = map.guard_dir;
+  let mut visited = vec![false; map.cell_count()];
+  let mut visited_count = 0usize;
+
+  let mut done = false;
+  let frames = std::iter::from_fn(move || {
+    if done {
+      return None;
     }
+
+    let ix = guard_pos.1 as usize * map.width + guard_pos.0 as usize;
+    if !visited[ix] {
+      visited[ix] = true;
+      visited_count += 1;
+    }
+
+    let frame = Frame::from_fn(
+      map.width,
+      map.height,
+      format!("visited: {visited_count}"),
+      |x, y| {
+        if (x as isize, y as isize) == guard_pos {
+          dir.guard_char()
+        } else if map.is_obstacle(x, y) {
+          '#'
+        } else if visited[y * map.width + x] {
+          'X'
+        } else {
+          '.'
+        }
+      },
+    );
+
+    match step_guard(&map, guard_pos, dir) {
+      Some((next_pos, next_dir)) => {
+        guard_pos = next_pos;
+        dir = next_dir;
+      },
+      None => done = true,
+    }
+
+    Some(frame)
   });
 
-  loop_count.load(Ordering::Relaxed)
+  Player::new()
+    .fps(120.)
+    .color('#', Rgb(110, 110, 110))
+    .color('X', Rgb(60, 120, 220))
+    .color('^', Rgb(230, 60, 60))
+    .color('<', Rgb(230, 60, 60))
+    .color('v', Rgb(230, 60, 60))
+    .color('>', Rgb(230, 60, 60))
+    .play(frames);
 }
 
-pub fn solve() {
-  let (guard_pos, grid) = parse_input(INPUT_BYTES);
-  let mut dir = Dir::Up;
-  let mut guard_pos = (guard_pos.0 as isize, guard_pos.1 as isize);
+#[test]
+fn example() {
+  let input = b"....#.....\n.........#\n..........\n..#.......\n.......#..\n..........\n.#..^.....\n........#.\n#.........\n......#...\n";
+  assert_eq!(part1(input), 41);
+  assert_eq!(part2(input), 6);
+}
 
-  let mut visited_positions: FxHashSet<(isize, isize)> = FxHashSet::default();
-  visited_positions.insert(guard_pos);
-  loop {
-    let next_pos = match dir {
-      Dir::Up => (guard_pos.0, guard_pos.1 - 1),
-      Dir::Left => (guard_pos.0 - 1, guard_pos.1),
-      Dir::Down => (guard_pos.0, guard_pos.1 + 1),
-      Dir::Right => (guard_pos.0 + 1, guard_pos.1),
-    };
-    if next_pos.0 < 0
-      || next_pos.0 >= GRID_SIZE as isize
-      || next_pos.1 < 0
-      || next_pos.1 >= GRID_SIZE as isize
-    {
-      break;
-    }
-    if grid[next_pos.1 as usize * GRID_SIZE + next_pos.0 as usize] {
-      dir = match dir {
-        Dir::Up => Dir::Right,
-        Dir::Left => Dir::Up,
-        Dir::Down => Dir::Left,
-        Dir::Right => Dir::Down,
-      };
+#[test]
+fn part2_matches_brute_force() {
+  let mut state = 0x853c49e6748fea9bu64;
+  let mut rand = |n: usize| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state as usize % n
+  };
+
+  // returns true if the guard loops, checking every single step
+  let loops = |map: &Map| {
+    let mut seen = std::collections::HashSet::new();
+    let mut pos = (map.guard_pos.0 as isize, map.guard_pos.1 as isize);
+    let mut dir = map.guard_dir;
+    while let Some((next_pos, next_dir)) = step_guard(map, pos, dir) {
+      (pos, dir) = (next_pos, next_dir);
+      if !seen.insert((pos, dir)) {
+        return true;
+      }
+    }
+    false
+  };
+
+  let mut tested = 0;
+  while tested < 100 {
+    let (width, height) = (rand(40) + 1, rand(40) + 1);
+    let density = rand(10) + 3;
+    let guard_ix = rand(width * height);
+    let guard = b"^>v<"[rand(4)];
+    let input = (0..height)
+      .map(|y| {
+        (0..width)
+          .map(|x| match y * width + x {
+            ix if ix == guard_ix => guard as char,
+            _ if rand(density) == 0 => '#',
+            _ => '.',
+          })
+          .collect::<String>()
+      })
+      .collect::<Vec<_>>()
+      .join("\n");
+
+    let map = parse_input(input.as_bytes());
+    if loops(&map) {
+      assert_eq!(visited_cells(&map), Err(PatrolLoops), "{input}");
+      assert_eq!(loop_obstructions(&map), Err(PatrolLoops), "{input}");
       continue;
-    } else {
-      guard_pos = next_pos;
-      visited_positions.insert(guard_pos);
     }
+    tested += 1;
+
+    let mut expected = 0;
+    for y in 0..height {
+      for x in 0..width {
+        if (x, y) == map.guard_pos || map.is_obstacle(x, y) {
+          continue;
+        }
+        let mut obstructed = map.clone();
+        obstructed.obstacles[y * width + x] = true;
+        if loops(&obstructed) {
+          expected += 1;
+        }
+      }
+    }
+    assert_eq!(part2(input.as_bytes()), expected, "{input}");
   }
+}
 
-  let out = visited_positions.len();
+#[test]
+fn trace_loop() {
+  let input = b"....#.....\n.........#\n..........\n..#.......\n.......#..\n..........\n.#..^.....\n........#.\n#.........\n......#...\n";
+  let map = parse_input(input);
 
-  println!("Part 1: {out}");
+  let trace = map.trace(None);
+  assert_eq!(trace.end, TraceEnd::LeftMap);
+  let visited = trace
+    .states
+    .iter()
+    .map(|state| state.pos)
+    .collect::<std::collections::HashSet<_>>();
+  assert_eq!(visited.len(), 41);
 
-  let loop_count = part2(INPUT_BYTES);
+  let trace = map.trace(Some((3, 6)));
+  // 5 moves up, 4 right, 5 down and 4 left, with a turn after each
+  assert_eq!(trace.end, TraceEnd::Loop { start: 0, len: 22 });
+  assert_eq!(trace.states[trace.turns[0]], GuardState {
+    pos: (4, 1),
+    dir: Dir::Right,
+  });
+  assert_eq!(
+    trace.render(&map),
+    "\
+....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.O^---+.
+........#.
+#.........
+......#...
+"
+  );
 
-  println!("Part 2: {loop_count}");
+  let mut json = Vec::new();
+  trace.write_json(&mut json).unwrap();
+  let json = String::from_utf8(json).unwrap();
+  assert_eq!(json.lines().count(), 23);
+  assert!(json.contains(r#"{"step":6,"x":4,"y":1,"dir":"right","turn":true}"#));
+  assert!(json.ends_with("{\"end\":\"loop\",\"cycle_start\":0,\"cycle_len\":22}\n"));
 }
 
-pub fn run(input: &[u8]) -> impl Display { part2(input) }
+#[test]
+fn looping_patrol() {
+  let map = parse_input(b".#...\n.^..#\n.....\n#....\n...#.\n");
+  assert_eq!(visited_cells(&map), Err(PatrolLoops));
+  assert_eq!(loop_obstructions(&map), Err(PatrolLoops));
+  assert_eq!(map.trace(None).end, TraceEnd::Loop { start: 0, len: 12 });
+}
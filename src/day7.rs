@@ -3,224 +3,728 @@
 #[cfg(feature = "local")]
 pub const INPUT: &'static str = include_str!("../inputs/day7.txt");
 
-use std::fmt::Display;
+use std::fmt::{Display, Write};
 
-use itertools::{repeat_n, Itertools};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use fxhash::{FxHashMap, FxHashSet};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+#[cfg(feature = "local")]
+use crate::runner::answer;
+
+/// Numbers in equations.  Every operation is checked, so anything that doesn't fit is reported as
+/// an `Overflow` rather than wrapping around.
+pub type Num = u128;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overflow;
+
+/// An equation from the input: the target followed by the numbers that have to combine into it
+pub type Equation = (Num, Vec<Num>);
+
+fn parse_input(input: &str) -> Result<Vec<Equation>, String> {
+  let parse_num = |line_ix: usize, n: &str| {
+    n.parse::<Num>()
+      .map_err(|err| format!("line {}: invalid number {n:?}: {err}", line_ix + 1))
+  };
 
-fn parse_input(input: &str) -> Vec<(usize, Vec<usize>)> {
   input
     .lines()
-    .map(|l| {
-      let (f, rest) = l.split_once(": ").unwrap();
-      (
-        f.parse().unwrap(),
-        rest
-          .split_ascii_whitespace()
-          .map(|n| n.parse().unwrap())
-          .collect(),
-      )
+    .enumerate()
+    .filter(|(_, l)| !l.trim().is_empty())
+    .map(|(line_ix, l)| {
+      let (target, rest) = l
+        .split_once(':')
+        .ok_or_else(|| format!("line {}: missing `:`", line_ix + 1))?;
+      let args = rest
+        .split_ascii_whitespace()
+        .map(|n| parse_num(line_ix, n))
+        .collect::<Result<Vec<_>, _>>()?;
+      if args.is_empty() {
+        return Err(format!("line {}: no numbers after the target", line_ix + 1));
+      }
+      Ok((parse_num(line_ix, target.trim())?, args))
     })
     .collect()
 }
 
+/// What an operator knows about the values of `a` that give `apply(a, b) == target`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inverse {
+  /// No value of `a` works
+  None,
+  /// Exactly this value of `a` works
+  One(Num),
+  /// The operator can't be undone here, so the solver has to try values of `a` forwards
+  Unknown,
+}
+
+/// A binary operator that can go between the numbers of an equation.
+pub trait Operator: Send + Sync {
+  /// Used when rendering expressions
+  fn symbol(&self) -> &str;
+
+  /// Returns `Ok(None)` if the result isn't defined, like when it would go negative, and
+  /// `Err(Overflow)` if it's too big to represent.
+  fn apply(&self, a: Num, b: Num) -> Result<Option<Num>, Overflow>;
+
+  /// Works out which `a` gives `apply(a, b) == target`, or returns `Err(Overflow)` if that `a` is
+  /// too big to represent.  Operators that can be undone should implement this since it lets the
+  /// solver work backwards from the target.
+  fn invert(&self, _target: Num, _b: Num) -> Result<Inverse, Overflow> { Ok(Inverse::Unknown) }
+
+  /// Returns true if `apply(a, b) >= a` for every `a` it's defined for, which lets the solver
+  /// abandon running totals that have already passed the target, including ones that overflow.
+  fn never_decreases(&self, _b: Num) -> bool { false }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Add;
+
+impl Operator for Add {
+  fn symbol(&self) -> &str { "+" }
+
+  fn apply(&self, a: Num, b: Num) -> Result<Option<Num>, Overflow> {
+    a.checked_add(b).map(Some).ok_or(Overflow)
+  }
+
+  fn invert(&self, target: Num, b: Num) -> Result<Inverse, Overflow> {
+    Ok(target.checked_sub(b).map_or(Inverse::None, Inverse::One))
+  }
+
+  fn never_decreases(&self, _b: Num) -> bool { true }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mul;
+
+impl Operator for Mul {
+  fn symbol(&self) -> &str { "*" }
+
+  fn apply(&self, a: Num, b: Num) -> Result<Option<Num>, Overflow> {
+    a.checked_mul(b).map(Some).ok_or(Overflow)
+  }
+
+  fn invert(&self, target: Num, b: Num) -> Result<Inverse, Overflow> {
+    Ok(match (target, b) {
+      // anything times zero is zero
+      (0, 0) => Inverse::Unknown,
+      (_, 0) => Inverse::None,
+      _ if target.is_multiple_of(b) => Inverse::One(target / b),
+      _ => Inverse::None,
+    })
+  }
+
+  fn never_decreases(&self, b: Num) -> bool { b >= 1 }
+}
+
+/// Appends the digits of `b` in `base` to the digits of `a`
 #[derive(Clone, Copy, Debug)]
-enum Op {
-  Add,
-  Mul,
-  Concat,
-}
-
-// who cares man
-// I don't want to fk around with logarithms
-fn digit_count(n: usize) -> usize {
-  if n < 10 {
-    1
-  } else if n < 100 {
-    2
-  } else if n < 1_000 {
-    3
-  } else if n < 10_000 {
-    4
-  } else if n < 100_000 {
-    5
-  } else if n < 1_000_000 {
-    6
-  } else if n < 10_000_000 {
-    7
-  } else if n < 100_000_000 {
-    8
-  } else if n < 1_000_000_000 {
-    9
-  } else {
-    unimplemented!()
-  }
-}
-
-fn get_multiplier_for_digit_count(digit_count: usize) -> usize {
-  10usize.pow(digit_count as u32 - 1)
-}
-
-impl Op {
-  fn apply(&self, a: usize, b: usize) -> usize {
-    match self {
-      Op::Add => a + b,
-      Op::Mul => a * b,
-      // Op::Concat => format!("{a}{b}").parse().unwrap(),
-      Op::Concat => {
-        let desired_a_digit_count = digit_count(b) + 1;
-        let a_multiplier = get_multiplier_for_digit_count(desired_a_digit_count);
-        a * a_multiplier + b
-      },
+pub struct Concat {
+  pub base: Num,
+}
+
+impl Concat {
+  pub const DECIMAL: Concat = Concat { base: 10 };
+
+  /// The power of the base that shifts `a` far enough left to make room for the digits of `b`, or
+  /// `None` if it doesn't fit.  Bases below 2 don't have digits, so nothing can be concatenated in
+  /// them.
+  fn multiplier(&self, b: Num) -> Option<Num> {
+    if self.base < 2 {
+      return None;
+    }
+    let mut multiplier = self.base;
+    while multiplier <= b {
+      multiplier = multiplier.checked_mul(self.base)?;
+    }
+    Some(multiplier)
+  }
+}
+
+impl Operator for Concat {
+  fn symbol(&self) -> &str { "||" }
+
+  fn apply(&self, a: Num, b: Num) -> Result<Option<Num>, Overflow> {
+    if self.base < 2 {
+      return Ok(None);
+    }
+    if a == 0 {
+      return Ok(Some(b));
+    }
+    let multiplier = self.multiplier(b).ok_or(Overflow)?;
+    a.checked_mul(multiplier)
+      .and_then(|shifted| shifted.checked_add(b))
+      .map(Some)
+      .ok_or(Overflow)
+  }
+
+  fn invert(&self, target: Num, b: Num) -> Result<Inverse, Overflow> {
+    if self.base < 2 {
+      return Ok(Inverse::None);
     }
+    // the target has to end with the digits of `b`, leaving `a` as the digits before them.  If
+    // shifting past `b` doesn't fit then neither does anything but `a = 0`.
+    Ok(match self.multiplier(b) {
+      Some(multiplier) if target % multiplier == b => Inverse::One(target / multiplier),
+      None if target == b => Inverse::One(0),
+      _ => Inverse::None,
+    })
+  }
+
+  fn never_decreases(&self, _b: Num) -> bool { true }
+}
+
+/// `a - b`, which isn't defined if it would go negative
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sub;
+
+impl Operator for Sub {
+  fn symbol(&self) -> &str { "-" }
+
+  fn apply(&self, a: Num, b: Num) -> Result<Option<Num>, Overflow> { Ok(a.checked_sub(b)) }
+
+  fn invert(&self, target: Num, b: Num) -> Result<Inverse, Overflow> {
+    target.checked_add(b).map(Inverse::One).ok_or(Overflow)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Xor;
+
+impl Operator for Xor {
+  fn symbol(&self) -> &str { "^" }
+
+  fn apply(&self, a: Num, b: Num) -> Result<Option<Num>, Overflow> { Ok(Some(a ^ b)) }
+
+  fn invert(&self, target: Num, b: Num) -> Result<Inverse, Overflow> {
+    Ok(Inverse::One(target ^ b))
   }
 }
 
 #[test]
 fn concat_correctness() {
-  assert_eq!(Op::Concat.apply(12, 34), 1234);
-  assert_eq!(Op::Concat.apply(543, 21), 54321);
-  assert_eq!(Op::Concat.apply(5432, 1004), 54321004);
-}
-
-// we pass target as an optimization.  Since all operators only increase the output, if we ever
-// exceed the output then we can bail out early.
-fn calc(args: &[usize], operators: &[Op], target: usize) -> usize {
-  let mut acc = operators[0].apply(unsafe { *args.get_unchecked(0) }, unsafe {
-    *args.get_unchecked(1)
-  });
-  for (arg_ix, arg) in args.iter().enumerate().skip(2) {
-    let op = unsafe { operators.get_unchecked(arg_ix - 1) };
-    acc = op.apply(acc, *arg);
-    if acc > target {
-      return usize::MAX;
-    }
-  }
-  acc
-}
-
-fn calc_n_target<const ARG_COUNT: usize>(args: &[usize], operators: &[Op], target: usize) -> usize {
-  let mut acc = operators[0].apply(unsafe { *args.get_unchecked(0) }, unsafe {
-    *args.get_unchecked(1)
-  });
-  for (arg_ix, arg) in (0..ARG_COUNT)
-    .skip(2)
-    .map(|arg_ix| (arg_ix, unsafe { args.get_unchecked(arg_ix) }))
-  {
-    let op = unsafe { operators.get_unchecked(arg_ix - 1) };
-    acc = op.apply(acc, *arg);
-    if arg_ix >= 3 && acc > target {
-      return usize::MAX;
-    }
-  }
-  acc
-}
-
-fn calc_n_no_target<const ARG_COUNT: usize>(args: &[usize], operators: &[Op]) -> usize {
-  let mut acc = operators[0].apply(unsafe { *args.get_unchecked(0) }, unsafe {
-    *args.get_unchecked(1)
-  });
-  for (arg_ix, arg) in (0..ARG_COUNT)
-    .skip(2)
-    .map(|arg_ix| (arg_ix, unsafe { args.get_unchecked(arg_ix) }))
-  {
-    let op = unsafe { operators.get_unchecked(arg_ix - 1) };
-    acc = op.apply(acc, *arg);
-  }
-  acc
-}
-
-fn calc_n<const ARG_COUNT: usize>(args: &[usize], operators: &[Op], target: usize) -> usize {
-  // if target > 100_000_000 {
-  //   calc_n_no_target::<ARG_COUNT>(args, operators)
-  // } else {
-  calc_n_target::<ARG_COUNT>(args, operators, target)
-  // }
-}
-
-fn calc_arb(args: &[usize], operators: &[Op], target: usize) -> usize {
-  match args.len() {
-    0 | 1 | 2 => unreachable!(),
-    // 2 => calc_n::<2>(args, operators, target),
-    3 => calc_n::<3>(args, operators, target),
-    4 => calc_n::<4>(args, operators, target),
-    5 => calc_n::<5>(args, operators, target),
-    6 => calc_n::<6>(args, operators, target),
-    7 => calc_n::<7>(args, operators, target),
-    8 => calc_n::<8>(args, operators, target),
-    9 => calc_n::<9>(args, operators, target),
-    10 => calc_n::<10>(args, operators, target),
-    11 => calc_n::<11>(args, operators, target),
-    12 => calc_n::<12>(args, operators, target),
-    other => unimplemented!("arg count: {other}"),
-  }
-}
-
-pub fn part1(input: &str) -> impl Display {
-  let input = parse_input(input);
+  assert_eq!(Concat::DECIMAL.apply(12, 34), Ok(Some(1234)));
+  assert_eq!(Concat::DECIMAL.apply(543, 21), Ok(Some(54321)));
+  assert_eq!(Concat::DECIMAL.apply(5432, 1004), Ok(Some(54321004)));
+  assert_eq!(Concat { base: 2 }.apply(0b101, 0b11), Ok(Some(0b10111)));
+  assert_eq!(Concat::DECIMAL.apply(Num::MAX / 10 + 1, 1), Err(Overflow));
+}
 
-  input
-    .iter()
-    .filter(|(res, args)| {
-      let ops = repeat_n([Op::Add, Op::Mul], args.len() - 1).multi_cartesian_product();
-      for ops in ops {
-        if calc_arb(args, &ops, *res) == *res {
-          return true;
+/// Adds up counts of ways, where `None` is a count too big to fit in a `Num`
+fn add_ways(a: Option<Num>, b: Option<Num>) -> Option<Num> { a?.checked_add(b?) }
+
+/// The values that some arguments can evaluate to, each with the value before the last argument and
+/// the operator that led to it
+type Layer = FxHashMap<Num, (Num, usize)>;
+
+/// Follows `val` back through the layers from `OperatorSet::reachable` to find the operators that
+/// lead to it
+fn forward_ops(layers: &[Layer], mut val: Num) -> Vec<usize> {
+  let mut ops = Vec::with_capacity(layers.len() - 1);
+  for layer in layers[1..].iter().rev() {
+    let (prev, op_ix) = layer[&val];
+    ops.push(op_ix);
+    val = prev;
+  }
+  ops.reverse();
+  ops
+}
+
+/// The operators that are allowed between the numbers of an equation
+#[derive(Default)]
+pub struct OperatorSet {
+  ops: Vec<Box<dyn Operator>>,
+}
+
+impl OperatorSet {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn with(mut self, op: impl Operator + 'static) -> Self {
+    self.ops.push(Box::new(op));
+    self
+  }
+
+  /// `+` and `*`
+  pub fn part1() -> Self { Self::new().with(Add).with(Mul) }
+
+  /// `+`, `*` and `||`
+  pub fn part2() -> Self { Self::part1().with(Concat::DECIMAL) }
+
+  pub fn ops(&self) -> &[Box<dyn Operator>] { &self.ops }
+
+  /// Returns true if some choice of operators between `args`, evaluated left to right, gives
+  /// `target`.  Returns `Err(Overflow)` if that can't be decided without going past the range of
+  /// `Num`.
+  pub fn can_make(&self, target: Num, args: &[Num]) -> Result<bool, Overflow> {
+    self.find_sequence(target, args).map(|seq| seq.is_some())
+  }
+
+  /// Finds one choice of operators between `args` that gives `target`, as indices into `ops()`.
+  ///
+  /// This works backwards from the target, undoing the last operator to find what the rest of the
+  /// arguments would have to evaluate to.  Most operators can only be undone for some targets, so
+  /// impossible branches get cut off right away instead of being evaluated in full.  Operators that
+  /// can't be undone fall back to trying every value the rest of the arguments can take.
+  ///
+  /// The search keeps its own stack, so any number of arguments is fine, and remembers which
+  /// targets turned out to be impossible for how many arguments so they're only explored once.
+  pub fn find_sequence(&self, target: Num, args: &[Num]) -> Result<Option<Vec<usize>>, Overflow> {
+    if args.is_empty() {
+      return Ok(None);
+    }
+
+    // `stack[i]` is what the first `args.len() - i` arguments have to evaluate to, along with the
+    // next operator to try undoing there
+    let mut stack = vec![(target, 0)];
+    let mut dead = FxHashSet::default();
+    let mut overflowed = false;
+    // the operators picked below the top of the stack, in the order they're applied
+    let picked = |stack: &[(Num, usize)]| {
+      stack[..stack.len() - 1]
+        .iter()
+        .rev()
+        .map(|&(_, next_op_ix)| next_op_ix - 1)
+        .collect::<Vec<_>>()
+    };
+
+    while let Some(&(target, op_ix)) = stack.last() {
+      let n = args.len() + 1 - stack.len();
+      if n == 1 {
+        if target == args[0] {
+          return Ok(Some(picked(&stack)));
         }
+        stack.pop();
+        continue;
       }
+      let Some(op) = self.ops.get(op_ix) else {
+        dead.insert((target, n));
+        stack.pop();
+        continue;
+      };
+      stack.last_mut().unwrap().1 += 1;
 
-      false
-    })
-    .map(|(val, _args)| *val)
-    .sum::<usize>()
-}
+      let (last, rest) = (args[n - 1], &args[..n - 1]);
+      match op.invert(target, last) {
+        Ok(Inverse::None) => (),
+        Ok(Inverse::One(rest_target)) =>
+          if !dead.contains(&(rest_target, n - 1)) {
+            stack.push((rest_target, 0));
+          },
+        Ok(Inverse::Unknown) => {
+          let bound = self.forward_bound(&**op, last, rest, target);
+          let Ok(layers) = self.reachable(rest, bound) else {
+            overflowed = true;
+            continue;
+          };
+          let mut found = None;
+          for &val in layers.last().unwrap().keys() {
+            match op.apply(val, last) {
+              Ok(res) if res == Some(target) => {
+                found = Some(val);
+                break;
+              },
+              Ok(_) => (),
+              Err(Overflow) => overflowed = true,
+            }
+          }
+          if let Some(val) = found {
+            let mut seq = forward_ops(&layers, val);
+            seq.push(op_ix);
+            seq.extend(picked(&stack));
+            return Ok(Some(seq));
+          }
+        },
+        Err(Overflow) => overflowed = true,
+      }
+    }
 
-const MAX_ARG_COUNT: usize = 13;
-static mut PERMUTED_OPS_CACHE: [*const Vec<Op>; MAX_ARG_COUNT] = [std::ptr::null(); MAX_ARG_COUNT];
+    // an overflow only counts as an error if nothing worked, since the equation is solvable either
+    // way if something does
+    if overflowed {
+      Err(Overflow)
+    } else {
+      Ok(None)
+    }
+  }
 
-fn compute_permuted_ops(arg_count: usize) -> Vec<Op> {
-  repeat_n([Op::Add, Op::Mul, Op::Concat], arg_count - 1)
-    .multi_cartesian_product()
-    .flatten()
-    .collect()
-}
+  /// Counts the choices of operators between `args` that give `target`.  Different operators count
+  /// separately even if they give the same value.  Unlike `can_make`, any overflow while applying
+  /// or undoing an operator is an error, since the count couldn't be exact otherwise, and so is a
+  /// count that doesn't fit in a `Num`.
+  ///
+  /// Like `find_sequence` this undoes operators from the last argument backwards, but one argument
+  /// at a time for every target at once, keeping count of how many ways each one leads to the
+  /// original target.
+  pub fn count_sequences(&self, target: Num, args: &[Num]) -> Result<Num, Overflow> {
+    if args.is_empty() {
+      return Ok(0);
+    }
+
+    // counts are `None` once they don't fit, which is only an error if they end up in the total
+    let mut count = Some(0);
+    let mut targets = FxHashMap::default();
+    targets.insert(target, Some(1));
+    for n in (2..=args.len()).rev() {
+      let (last, rest) = (args[n - 1], &args[..n - 1]);
+      let mut next_targets = FxHashMap::default();
+      for (&target, &ways) in &targets {
+        for op in &self.ops {
+          match op.invert(target, last)? {
+            Inverse::None => (),
+            Inverse::One(rest_target) => {
+              let rest_ways = next_targets.entry(rest_target).or_insert(Some(0));
+              *rest_ways = add_ways(*rest_ways, ways);
+            },
+            Inverse::Unknown => {
+              let bound = self.forward_bound(&**op, last, rest, target);
+              for (val, val_ways) in self.reachable_counts(rest, bound)? {
+                if op.apply(val, last)? == Some(target) {
+                  let ways = val_ways.zip(ways).and_then(|(a, b)| a.checked_mul(b));
+                  count = add_ways(count, ways);
+                }
+              }
+            },
+          }
+        }
+      }
+      targets = next_targets;
+    }
+
+    let ways = targets.get(&args[0]).copied().unwrap_or(Some(0));
+    add_ways(count, ways).ok_or(Overflow)
+  }
 
-fn init_permuted_ops() {
-  for i in 3..MAX_ARG_COUNT {
-    let ops = compute_permuted_ops(i);
-    unsafe { PERMUTED_OPS_CACHE[i] = Box::into_raw(Box::new(ops)) };
+  /// The largest value of `rest` worth keeping when `op` with `last` can't be undone.  If nothing
+  /// can make a running total smaller, values that are already past the target can't lead back to
+  /// it.
+  fn forward_bound(&self, op: &dyn Operator, last: Num, rest: &[Num], target: Num) -> Option<Num> {
+    let prune = op.never_decreases(last)
+      && rest[1..]
+        .iter()
+        .all(|&b| self.ops.iter().all(|op| op.never_decreases(b)));
+    prune.then_some(target)
+  }
+
+  /// Every value that `args` can evaluate to, leaving out values above `bound` if it's given.
+  /// Values that overflow can only be left out when there's a bound.
+  ///
+  /// There's a layer for each argument, holding the values up to it along with the value before
+  /// and the operator that led to each one, so the last layer holds the final values.
+  fn reachable(&self, args: &[Num], bound: Option<Num>) -> Result<Vec<Layer>, Overflow> {
+    let mut first = FxHashMap::default();
+    first.insert(args[0], (args[0], usize::MAX));
+    let mut layers = vec![first];
+    for &arg in &args[1..] {
+      let mut next_vals = FxHashMap::default();
+      for &val in layers.last().unwrap().keys() {
+        for (op_ix, op) in self.ops.iter().enumerate() {
+          match (op.apply(val, arg), bound) {
+            (Ok(Some(next)), Some(bound)) if next > bound => (),
+            (Ok(Some(next)), _) => {
+              next_vals.entry(next).or_insert((val, op_ix));
+            },
+            (Ok(None), _) | (Err(Overflow), Some(_)) => (),
+            (Err(Overflow), None) => return Err(Overflow),
+          }
+        }
+      }
+      layers.push(next_vals);
+    }
+    Ok(layers)
+  }
+
+  /// Like `reachable`, along with how many choices of operators give each value, or `None` if
+  /// that doesn't fit in a `Num`
+  fn reachable_counts(
+    &self,
+    args: &[Num],
+    bound: Option<Num>,
+  ) -> Result<FxHashMap<Num, Option<Num>>, Overflow> {
+    let mut counts = FxHashMap::default();
+    counts.insert(args[0], Some(1));
+    for &arg in &args[1..] {
+      let mut next_counts = FxHashMap::default();
+      for (&val, &n) in &counts {
+        for op in &self.ops {
+          match (op.apply(val, arg), bound) {
+            (Ok(Some(next)), Some(bound)) if next > bound => (),
+            (Ok(Some(next)), _) => {
+              let count = next_counts.entry(next).or_insert(Some(0));
+              *count = add_ways(*count, n);
+            },
+            (Ok(None), _) | (Err(Overflow), Some(_)) => (),
+            (Err(Overflow), None) => return Err(Overflow),
+          }
+        }
+      }
+      counts = next_counts;
+    }
+    Ok(counts)
+  }
+
+  /// Writes out `args` with the operators picked by `seq` between them, like `81 + 40 * 27`
+  pub fn render(&self, args: &[Num], seq: &[usize]) -> String {
+    let mut expression = args[0].to_string();
+    for (&op_ix, arg) in seq.iter().zip(&args[1..]) {
+      write!(expression, " {} {arg}", self.ops[op_ix].symbol()).unwrap();
+    }
+    expression
+  }
+
+  /// Solves `target` from `args` and, if `count` is set, counts every way of doing it
+  pub fn witness(&self, target: Num, args: &[Num], count: bool) -> Result<Witness, Overflow> {
+    let expression = self
+      .find_sequence(target, args)?
+      .map(|seq| self.render(args, &seq));
+    Ok(Witness {
+      target,
+      solvable: expression.is_some(),
+      expression,
+      count: count
+        .then(|| self.count_sequences(target, args))
+        .transpose()?,
+    })
   }
 }
 
-fn get_permuted_ops(arg_count: usize) -> &'static [Op] {
-  unsafe { (*PERMUTED_OPS_CACHE[arg_count]).as_slice() }
+/// How an equation can be made with an `OperatorSet`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Witness {
+  pub target: Num,
+  pub solvable: bool,
+  /// One choice of operators that gives the target.  Like in the puzzle, it's evaluated left to
+  /// right rather than with the usual precedence.
+  pub expression: Option<String>,
+  /// How many choices of operators give the target, if they were counted
+  pub count: Option<Num>,
 }
 
-pub fn part2(input: &str) -> impl Display {
-  init_permuted_ops();
+impl Display for Witness {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.expression {
+      Some(expression) => write!(f, "{}: {expression}", self.target)?,
+      None => write!(f, "{}: unsolvable", self.target)?,
+    }
+    if let Some(count) = self.count {
+      write!(f, " ({count} {})", if count == 1 { "way" } else { "ways" })?;
+    }
+    Ok(())
+  }
+}
 
-  let input = parse_input(input);
+/// Finds a `Witness` for every equation in `input`, in order.  Returns an error if the input is
+/// malformed or if anything overflows along the way.
+pub fn witnesses(input: &str, ops: &OperatorSet, count: bool) -> Result<Vec<Witness>, String> {
+  let equations = parse_input(input)?;
+  equations
+    .par_iter()
+    .enumerate()
+    .map(|(ix, (target, args))| {
+      ops
+        .witness(*target, args, count)
+        .map_err(|Overflow| format!("equation {} ({target}) overflows while solving", ix + 1))
+    })
+    .collect()
+}
 
-  input
+/// Sums the targets of the equations in `input` that can be made with `ops`.  Returns an error if
+/// the input is malformed or if anything overflows along the way.
+pub fn sum_solvable(input: &str, ops: &OperatorSet) -> Result<Num, String> {
+  let equations = parse_input(input)?;
+  let solvable = equations
     .par_iter()
-    .filter(|(res, args)| {
-      let ops = get_permuted_ops(args.len());
-      let chunk_size = args.len() - 1;
-      ops.chunks_exact(chunk_size)
-        // .par_bridge()
-        .any(|ops| calc_arb(args, ops, *res) == *res)
+    .enumerate()
+    .map(|(ix, (target, args))| {
+      ops
+        .can_make(*target, args)
+        .map(|solvable| if solvable { *target } else { 0 })
+        .map_err(|Overflow| format!("equation {} ({target}) overflows while solving", ix + 1))
     })
-    .map(|(val, _args)| *val)
-    .sum::<usize>()
+    .collect::<Result<Vec<_>, _>>()?;
+
+  solvable.iter().try_fold(0 as Num, |sum, &target| {
+    sum
+      .checked_add(target)
+      .ok_or_else(|| "sum of solvable targets overflows".to_owned())
+  })
 }
 
+pub fn part1(input: &str) -> impl Display { sum_solvable(input, &OperatorSet::part1()).unwrap() }
+
+pub fn part2(input: &str) -> impl Display { sum_solvable(input, &OperatorSet::part2()).unwrap() }
+
 #[cfg(feature = "local")]
 pub fn solve() {
   let part1 = part1(INPUT);
-  println!("Part 1: {part1}");
+  answer(1, part1);
 
   let part2 = part2(INPUT);
-  println!("Part 2: {part2}");
+  answer(2, part2);
 }
 
 pub fn run(input: &str) -> impl Display { part2(input) }
+
+#[test]
+fn example() {
+  let input = "190: 10 19\n3267: 81 40 27\n83: 17 5\n156: 15 6\n7290: 6 8 6 15\n161011: 16 10 \
+               13\n192: 17 8 14\n21037: 9 7 18 13\n292: 11 6 16 20\n";
+  assert_eq!(part1(input).to_string(), "3749");
+  assert_eq!(part2(input).to_string(), "11387");
+}
+
+#[test]
+fn operator_sets_match_forward_evaluation() {
+  use itertools::{repeat_n, Itertools};
+
+  let mut state = 0x9e3779b97f4a7c15u64;
+  let mut rand = |n: usize| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state as usize % n
+  };
+
+  /// Never decreases but can't be undone, so it's only ever evaluated forwards
+  struct Max;
+  impl Operator for Max {
+    fn symbol(&self) -> &str { "max" }
+
+    fn apply(&self, a: Num, b: Num) -> Result<Option<Num>, Overflow> { Ok(Some(a.max(b))) }
+
+    fn never_decreases(&self, _b: Num) -> bool { true }
+  }
+
+  let sets = [
+    OperatorSet::part1(),
+    OperatorSet::part2(),
+    OperatorSet::new().with(Add).with(Sub).with(Xor),
+    OperatorSet::new()
+      .with(Mul)
+      .with(Concat { base: 2 })
+      .with(Max),
+    OperatorSet::part2().with(Max),
+  ];
+  for _ in 0..3000 {
+    let ops = &sets[rand(sets.len())];
+    let args = (0..rand(5) + 1)
+      .map(|_| rand(20) as Num)
+      .collect::<Vec<_>>();
+    let results = repeat_n(ops.ops().iter(), args.len() - 1)
+      .multi_cartesian_product()
+      .filter_map(|seq| {
+        seq
+          .iter()
+          .zip(&args[1..])
+          .try_fold(args[0], |acc, (op, &arg)| op.apply(acc, arg).unwrap())
+      })
+      .collect::<Vec<_>>();
+
+    // pick a target that's reachable about half of the time
+    let target = if rand(2) == 0 && !results.is_empty() {
+      results[rand(results.len())]
+    } else {
+      rand(2000) as Num
+    };
+    assert_eq!(
+      ops.can_make(target, &args).unwrap(),
+      results.contains(&target),
+      "{target}: {args:?}"
+    );
+    assert_eq!(
+      ops.count_sequences(target, &args).unwrap(),
+      results.iter().filter(|&&res| res == target).count() as Num,
+      "{target}: {args:?}"
+    );
+    if let Some(seq) = ops.find_sequence(target, &args).unwrap() {
+      let res = seq
+        .iter()
+        .zip(&args[1..])
+        .try_fold(args[0], |acc, (&op_ix, &arg)| {
+          ops.ops()[op_ix].apply(acc, arg).unwrap()
+        });
+      assert_eq!(res, Some(target), "{target}: {args:?}");
+    }
+  }
+}
+
+#[test]
+fn witness_expressions() {
+  let input = "190: 10 19\n3267: 81 40 27\n83: 17 5\n7290: 6 8 6 15\n292: 11 6 16 20\n";
+  let render = |ops: &OperatorSet| {
+    witnesses(input, ops, true)
+      .unwrap()
+      .iter()
+      .map(|witness| witness.to_string())
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(render(&OperatorSet::part1()), [
+    "190: 10 * 19 (1 way)",
+    "3267: 81 * 40 + 27 (2 ways)",
+    "83: unsolvable (0 ways)",
+    "7290: unsolvable (0 ways)",
+    "292: 11 + 6 * 16 + 20 (1 way)",
+  ]);
+  assert_eq!(
+    render(&OperatorSet::part2())[3],
+    "7290: 6 * 8 || 6 * 15 (1 way)"
+  );
+
+  let witness = OperatorSet::part1()
+    .witness(3267, &[81, 40, 27], false)
+    .unwrap();
+  assert!(witness.solvable);
+  assert_eq!(witness.count, None);
+}
+
+#[test]
+fn wide_and_overflowing_equations() {
+  // 2^50 * 2^50 and a concatenation that doesn't fit in a u64
+  let input = "1267650600228229401496703205376: 1125899906842624 \
+               1125899906842624\n12345678901234567890123: 12345678901 234567890123\n";
+  assert_eq!(
+    sum_solvable(input, &OperatorSet::part1()),
+    Ok(1267650600228229401496703205376)
+  );
+  assert_eq!(
+    sum_solvable(input, &OperatorSet::part2()),
+    Ok(1267650600228229401496703205376 + 12345678901234567890123)
+  );
+
+  // any number of operands
+  let args = (0..200).map(|i| 1_000_003 + 2 * i).collect::<Vec<Num>>();
+  let sum = args.iter().sum::<Num>();
+  assert_eq!(OperatorSet::part1().can_make(sum, &args), Ok(true));
+  assert_eq!(OperatorSet::part1().can_make(sum + 1, &args), Ok(false));
+  // far more than would fit on the call stack if the search recursed once per operand
+  assert_eq!(
+    OperatorSet::part1().can_make(100_000, &[1; 100_000]),
+    Ok(true)
+  );
+
+  // every operator can be undone for every target, so only remembering dead ends keeps this fast
+  assert_eq!(OperatorSet::part2().can_make(12345678, &[1; 30]), Ok(false));
+  // `+ 1` adds one and `* 1` does nothing, so this is 29 choose 14
+  assert_eq!(
+    OperatorSet::part1().count_sequences(15, &[1; 30]),
+    Ok(77558760)
+  );
+  assert_eq!(
+    OperatorSet::part1().count_sequences(1000, &[1; 1000]),
+    Ok(1)
+  );
+  assert_eq!(
+    OperatorSet::part1().count_sequences(500, &[1; 1000]),
+    Err(Overflow)
+  );
+
+  // undoing the subtraction needs a value bigger than `Num::MAX`
+  let max = Num::MAX;
+  let subtract = OperatorSet::new().with(Sub);
+  assert_eq!(subtract.can_make(max, &[5, 1]), Err(Overflow));
+  assert!(sum_solvable(&format!("{max}: 5 1"), &subtract).is_err());
+  // ...but it doesn't matter if another operator solves it
+  assert_eq!(subtract.with(Add).can_make(max, &[max - 1, 1]), Ok(true));
+
+  assert!(sum_solvable(&format!("{max}: {max}\n1: 1\n"), &OperatorSet::part1()).is_err());
+  assert!(sum_solvable(&format!("1: {max}0"), &OperatorSet::part1()).is_err());
+}
@@ -8,19 +8,72 @@ use std::{
   },
 };
 
+#[cfg(feature = "local")]
+use crate::runner::answer;
+
 #[cfg(feature = "local")]
 pub const INPUT: &[u8] = include_bytes!("../inputs/day8.txt");
 
 const GRID_SIZE: usize = 50;
 const LINE_SIZE: usize = GRID_SIZE + 1;
+const MAX_ANTENNAS_PER_FREQUENCY: usize = 4;
+
+/// Checks the assumptions that `parse_input` makes about the input, all of which hold for the real
+/// puzzle inputs:
+///
+///  * there are at least `GRID_SIZE` lines of `GRID_SIZE` chars, each followed by a separator
+///  * every antenna is in `0-9`, `A-Z`, or `a-z` (anything below `0` is treated as empty space)
+///  * no antenna sits in the separator column
+///  * there are at most `MAX_ANTENNAS_PER_FREQUENCY` antennas of each frequency
+pub fn validate_input(input: &[u8]) -> Result<(), String> {
+  if input.len() < LINE_SIZE * GRID_SIZE {
+    return Err(format!(
+      "input is {} bytes, but at least {} are required",
+      input.len(),
+      LINE_SIZE * GRID_SIZE
+    ));
+  }
+
+  let mut counts_by_char = [0usize; 123 - 47];
+  for (i, &c) in input[..LINE_SIZE * GRID_SIZE].iter().enumerate() {
+    if c < '0' as u8 {
+      continue;
+    }
+
+    let (x, y) = (i % LINE_SIZE, i / LINE_SIZE);
+    if !c.is_ascii_alphanumeric() {
+      return Err(format!("invalid antenna {:?} at ({x}, {y})", c as char));
+    }
+    if x == GRID_SIZE {
+      return Err(format!("antenna {:?} at ({x}, {y}) is outside the grid", c as char));
+    }
+
+    let count = &mut counts_by_char[c as usize - 47];
+    *count += 1;
+    if *count > MAX_ANTENNAS_PER_FREQUENCY {
+      return Err(format!(
+        "more than {MAX_ANTENNAS_PER_FREQUENCY} antennas with frequency {:?}",
+        c as char
+      ));
+    }
+  }
 
+  Ok(())
+}
+
+/// Records the position of every antenna in the first free (`EMPTY`) slot of its frequency's entry in
+/// `positions_by_char`, which is indexed by `char - 47`.
+///
+/// Returns an error without touching any of the outputs if `input` fails `validate_input`.
 #[inline(always)]
-fn parse_input<const EMPTY: i32, const ANTENNAS_ARE_ANTINODES: bool>(
-  positions_by_char: &mut [[(i32, i32); 4]; 123 - 47],
+pub fn parse_input<const EMPTY: i32, const ANTENNAS_ARE_ANTINODES: bool>(
+  positions_by_char: &mut [[(i32, i32); MAX_ANTENNAS_PER_FREQUENCY]; 123 - 47],
   input: &[u8],
-  antinodes: &mut [[bool; 50]; 50],
+  antinodes: &mut [[bool; GRID_SIZE]; GRID_SIZE],
   antinode_count: &mut usize,
-) {
+) -> Result<(), String> {
+  validate_input(input)?;
+
   let mut i = 0usize;
   while i < LINE_SIZE * GRID_SIZE {
     let char_ptr = unsafe { input.as_ptr().add(i) };
@@ -72,6 +125,8 @@ fn parse_input<const EMPTY: i32, const ANTENNAS_ARE_ANTINODES: bool>(
 
     i += 1;
   }
+
+  Ok(())
 }
 
 pub fn part1(input: &[u8]) -> impl Display {
@@ -86,7 +141,8 @@ pub fn part1(input: &[u8]) -> impl Display {
     input,
     &mut antinodes,
     &mut antinode_count,
-  );
+  )
+  .unwrap();
 
   for positions in positions_by_char {
     if positions[0].0 == EMPTY {
@@ -161,7 +217,8 @@ pub fn part2(input: &[u8]) -> impl Display {
     input,
     &mut antinodes,
     &mut antinode_count,
-  );
+  )
+  .unwrap();
 
   for positions in positions_by_char {
     if positions[0].0 == EMPTY {
@@ -218,10 +275,10 @@ pub fn part2(input: &[u8]) -> impl Display {
 #[cfg(feature = "local")]
 pub fn solve() {
   let out = part1(INPUT);
-  println!("Part 1: {out}");
+  answer(1, out);
 
   let out = part2(INPUT);
-  println!("Part 2: {out}");
+  answer(2, out);
 }
 
 pub fn run(input: &[u8]) -> impl Display { part2(input) }
@@ -89,6 +89,9 @@ use std::{
   simd::{cmp::SimdPartialOrd, u16x16, u8x32, u8x64, u8x8},
 };
 
+#[cfg(feature = "local")]
+use crate::runner::answer;
+
 #[cfg(feature = "local")]
 pub const INPUT: &'static [u8; 20_000] = include_bytes!("../inputs/day9.txt");
 
@@ -161,8 +164,49 @@ fn aligned_vec<T>(n_bytes: usize) -> Vec<T> {
 // }
 
 const MAX_ID: usize = 9_999;
+/// `MAX_ID + 1` interleaved file sizes and free space counts, with the trailing newline standing in
+/// for the free space after the last file
+const INPUT_LEN: usize = (MAX_ID + 1) * 2;
+
+/// Checks the assumptions that `parse_input_p2` and `part2` make about the input, all of which hold
+/// for the real puzzle inputs:
+///
+///  * the input is exactly `INPUT_LEN` bytes long, which makes it a whole number of SIMD vectors
+///  * the input is aligned to 64 bytes so that aligned loads can be used
+///  * every byte except the last is an ASCII digit and no file has a size of zero
+///  * the last byte is a newline.  It gets parsed as the free space after the last file, and since
+///    that wraps around to a huge value it serves as the sentinel that stops the free space search.
+pub fn validate_input_p2(input: &[u8]) -> Result<(), String> {
+  if input.len() != INPUT_LEN {
+    return Err(format!(
+      "input is {} bytes, but exactly {INPUT_LEN} are required",
+      input.len()
+    ));
+  }
+  if !input.as_ptr().is_aligned_to(std::mem::align_of::<u8x64>()) {
+    return Err("input must be aligned to 64 bytes".to_owned());
+  }
+
+  let (digits, newline) = input.split_at(INPUT_LEN - 1);
+  if let Some(ix) = digits.iter().position(|c| !c.is_ascii_digit()) {
+    return Err(format!("expected a digit at index {ix}, found {:?}", digits[ix] as char));
+  }
+  if let Some(id) = digits.iter().step_by(2).position(|&c| c == '0' as u8) {
+    return Err(format!("file {id} has a size of zero"));
+  }
+  if newline != b"\n" {
+    return Err("input must end with a newline".to_owned());
+  }
+
+  Ok(())
+}
+
+/// (original file sizes, free space after each file, files stored in each span)
+pub type ParsedInputP2 = (Vec<u8>, Vec<u8>, Vec<MiniVec>);
+
+pub fn parse_input_p2(input: &[u8]) -> Result<ParsedInputP2, String> {
+  validate_input_p2(input)?;
 
-fn parse_input_p2(input: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<MiniVec>) {
   let id_count = if input.len() % 2 == 1 {
     input.len() / 2 + 1
   } else {
@@ -303,8 +347,6 @@ fn parse_input_p2(input: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<MiniVec>) {
     // }
   }
 
-  debug_assert!(input.as_ptr().is_aligned_to(std::mem::align_of::<u8x64>()));
-
   const VECTOR_LEN: usize = 32;
   const STORE_VECTOR_LEN: usize = VECTOR_LEN / 2;
   let batch_count = input.len() / VECTOR_LEN;
@@ -370,7 +412,7 @@ fn parse_input_p2(input: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<MiniVec>) {
   }
    */
 
-  (orig_counts, empty_spaces, slots)
+  Ok((orig_counts, empty_spaces, slots))
 }
 
 fn compute_fs(input: &[(u32, u32)]) -> Vec<Option<u32>> {
@@ -427,7 +469,7 @@ pub fn part1(input: &[u8]) -> usize {
 
 #[repr(align(2))]
 #[derive(Debug, Clone, Copy, PartialEq)]
-struct Slot {
+pub struct Slot {
   pub id: u16,
   // count is elided here because it can be referred back to in the original counts array, saving
   // space and work.
@@ -441,7 +483,7 @@ impl Slot {
 
 #[repr(align(16))]
 #[derive(Clone, Debug, PartialEq)]
-struct MiniVec {
+pub struct MiniVec {
   pub len: u16,
   pub elements: [Slot; 6],
   pub padding: u16,
@@ -534,7 +576,7 @@ impl Slot {
 }
 
 pub fn part2(raw_input: &[u8]) -> usize {
-  let (counts, mut empty_spaces, mut slots) = parse_input_p2(raw_input);
+  let (counts, mut empty_spaces, mut slots) = parse_input_p2(raw_input).unwrap();
 
   fn checksum(
     slots: &[Slot],
@@ -708,11 +750,11 @@ pub fn solve() {
 
   let out = part1(aligned_input);
 
-  println!("Part 1: {}", out);
+  answer(1, out);
 
   let out = part2(aligned_input);
 
-  println!("Part 2: {}", out);
+  answer(2, out);
 }
 
 pub fn run(input: &[u8]) -> impl Display { part2(input) }
@@ -0,0 +1,203 @@
+//! Exports grids to PPM or PNG images for debugging.  Each grid cell becomes a `scale`x`scale`
+//! square of pixels, and overlays like paths or highlighted cells can be painted on top before
+//! writing.
+
+use std::{
+  fs::File,
+  io::{self, BufWriter, Write},
+  path::{Path, PathBuf},
+};
+
+pub use crate::viz::Rgb;
+
+/// Returns a distinct color for each `i` by stepping around the color wheel by the golden ratio,
+/// which keeps nearby indices far apart in hue.
+pub fn categorical_color(i: usize) -> Rgb {
+  let hue = (i as f64 * 0.618_033_988_75).fract() * 6.;
+  let (saturation, value) = (0.65, 0.9);
+
+  let chroma = value * saturation;
+  let x = chroma * (1. - (hue % 2. - 1.).abs());
+  let (r, g, b) = match hue as usize {
+    0 => (chroma, x, 0.),
+    1 => (x, chroma, 0.),
+    2 => (0., chroma, x),
+    3 => (0., x, chroma),
+    4 => (x, 0., chroma),
+    _ => (chroma, 0., x),
+  };
+  let m = value - chroma;
+  let to_u8 = |c: f64| ((c + m) * 255.).round() as u8;
+  Rgb(to_u8(r), to_u8(g), to_u8(b))
+}
+
+#[derive(Clone, Debug)]
+pub struct Image {
+  width: usize,
+  height: usize,
+  scale: usize,
+  cells: Vec<Rgb>,
+}
+
+impl Image {
+  pub fn new(width: usize, height: usize, background: Rgb) -> Self {
+    Image {
+      width,
+      height,
+      scale: 1,
+      cells: vec![background; width * height],
+    }
+  }
+
+  /// Builds an image with one cell per grid element, colored by `color(x, y, elem)`.
+  pub fn from_grid<T>(grid: &[Vec<T>], color: impl Fn(usize, usize, &T) -> Rgb) -> Self {
+    let height = grid.len();
+    let width = grid.first().map(|row| row.len()).unwrap_or(0);
+    let mut img = Self::new(width, height, Rgb(0, 0, 0));
+    for (y, row) in grid.iter().enumerate() {
+      for (x, elem) in row.iter().enumerate().take(width) {
+        img.set(x, y, color(x, y, elem));
+      }
+    }
+    img
+  }
+
+  /// Sets how many pixels wide and tall each cell is in the written image.
+  pub fn scale(mut self, scale: usize) -> Self {
+    self.scale = scale.max(1);
+    self
+  }
+
+  pub fn width(&self) -> usize { self.width }
+
+  pub fn height(&self) -> usize { self.height }
+
+  /// Colors the cell at `(x, y)`.  Coordinates outside of the image are ignored.
+  pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+    if x < self.width && y < self.height {
+      self.cells[y * self.width + x] = color;
+    }
+  }
+
+  /// Paints all of `coords` with `color`, for things like paths or sets of highlighted cells.
+  pub fn overlay(&mut self, coords: impl IntoIterator<Item = (usize, usize)>, color: Rgb) {
+    for (x, y) in coords {
+      self.set(x, y, color);
+    }
+  }
+
+  fn pixel_dims(&self) -> (usize, usize) { (self.width * self.scale, self.height * self.scale) }
+
+  fn to_rgb_bytes(&self) -> Vec<u8> {
+    let (pixel_width, pixel_height) = self.pixel_dims();
+    let mut out = Vec::with_capacity(pixel_width * pixel_height * 3);
+    for row in self.cells.chunks(self.width.max(1)) {
+      let start = out.len();
+      for &Rgb(r, g, b) in row {
+        for _ in 0..self.scale {
+          out.extend_from_slice(&[r, g, b]);
+        }
+      }
+      let end = out.len();
+      for _ in 1..self.scale {
+        out.extend_from_within(start..end);
+      }
+    }
+    out
+  }
+
+  pub fn write_ppm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+    let (pixel_width, pixel_height) = self.pixel_dims();
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(out, "P6\n{pixel_width} {pixel_height}\n255\n")?;
+    out.write_all(&self.to_rgb_bytes())?;
+    out.flush()
+  }
+
+  pub fn write_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+    let (pixel_width, pixel_height) = self.pixel_dims();
+    let out = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(out, pixel_width as u32, pixel_height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&self.to_rgb_bytes())?;
+    writer.finish()?;
+    Ok(())
+  }
+
+  /// Writes a PNG or PPM image depending on the extension of `path`.
+  pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    match path.extension().and_then(|ext| ext.to_str()) {
+      Some("png") => self.write_png(path),
+      Some("ppm") => self.write_ppm(path),
+      _ => Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("unsupported image format: {}", path.display()),
+      )),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+  Ppm,
+  Png,
+}
+
+impl Format {
+  fn extension(&self) -> &'static str {
+    match self {
+      Format::Ppm => "ppm",
+      Format::Png => "png",
+    }
+  }
+}
+
+/// Writes a sequence of images to numbered files like `dir/frame_00000.png`, for turning
+/// simulations into videos with something like
+/// `ffmpeg -i dir/frame_%05d.png out.mp4`.
+pub struct FrameSequence {
+  dir: PathBuf,
+  format: Format,
+  frame_ix: usize,
+}
+
+impl FrameSequence {
+  pub fn new(dir: impl Into<PathBuf>, format: Format) -> io::Result<Self> {
+    let dir = dir.into();
+    std::fs::create_dir_all(&dir)?;
+    Ok(FrameSequence {
+      dir,
+      format,
+      frame_ix: 0,
+    })
+  }
+
+  /// Writes `img` as the next frame in the sequence, returning the path it was written to.
+  pub fn push(&mut self, img: &Image) -> io::Result<PathBuf> {
+    let path = self.dir.join(format!(
+      "frame_{:05}.{}",
+      self.frame_ix,
+      self.format.extension()
+    ));
+    img.save(&path)?;
+    self.frame_ix += 1;
+    Ok(path)
+  }
+}
+
+#[test]
+fn ppm_output() {
+  let grid = vec![vec![0u8, 1], vec![2, 3]];
+  let img = Image::from_grid(&grid, |x, y, &v| Rgb(x as u8, y as u8, v * 10));
+  let path = std::env::temp_dir().join(format!("aoc_image_test_{}.ppm", std::process::id()));
+  img.save(&path).unwrap();
+  let bytes = std::fs::read(&path).unwrap();
+  std::fs::remove_file(&path).unwrap();
+
+  let mut expected = b"P6\n2 2\n255\n".to_vec();
+  expected.extend_from_slice(&[0, 0, 0, 1, 0, 10, 0, 1, 20, 1, 1, 30]);
+  assert_eq!(bytes, expected);
+}
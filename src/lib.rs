@@ -10,6 +10,9 @@
 extern crate cached;
 
 pub mod helpers;
+pub mod image;
+pub mod runner;
+pub mod viz;
 
 pub mod day1;
 pub mod day10;
@@ -1,4 +1,39 @@
+use std::time::Instant;
+
+use aoc_2024::runner::{self, RunOptions};
+
+fn parse_run_options(args: &[String]) -> RunOptions {
+  let mut opts = RunOptions::default();
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--parallel" => opts.parallel = true,
+      "--threads" => {
+        let threads = args
+          .next()
+          .and_then(|n| n.parse().ok())
+          .expect("--threads requires a number");
+        opts.threads = Some(threads);
+      },
+      _ => panic!("unknown argument: {arg}"),
+    }
+  }
+  opts
+}
+
 fn main() {
+  let args = std::env::args().skip(1).collect::<Vec<_>>();
+  if args.first().map(String::as_str) == Some("all") {
+    let opts = parse_run_options(&args[1..]);
+    let start = Instant::now();
+    #[cfg(feature = "local")]
+    let results = runner::run_all(runner::DAYS, opts);
+    #[cfg(not(feature = "local"))]
+    let results = runner::run_all(&[], opts);
+    runner::print_summary(&results, opts, start.elapsed());
+    return;
+  }
+
   // aoc_2024::day1::solve();
   // aoc_2024::day2::solve();
   // aoc_2024::day3::solve();
@@ -0,0 +1,286 @@
+//! Runs all of the days at once, optionally in parallel, and prints a summary table of the answers
+//! and timings.
+//!
+//! Days report their answers through `answer`, which prints them as usual when a day is run on its
+//! own but records them for the summary when the day is run from here.  Any other output a day
+//! prints should be skipped when `quiet` returns true so that it doesn't get mixed in with the
+//! summary.
+
+use std::{
+  cell::RefCell,
+  fmt::Display,
+  panic::{self, AssertUnwindSafe},
+  sync::atomic::{AtomicBool, Ordering},
+  time::{Duration, Instant},
+};
+
+use rayon::prelude::*;
+
+struct Collector {
+  last_answer_at: Instant,
+  parts: Vec<PartResult>,
+}
+
+thread_local! {
+  static COLLECTOR: RefCell<Option<Collector>> = const { RefCell::new(None) };
+}
+
+/// Set while `run_all` is running.  This is global rather than part of the collector since days may
+/// print from other threads.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Whether days should skip printing anything other than their answers.
+pub fn quiet() -> bool { QUIET.load(Ordering::Relaxed) }
+
+/// Reports the answer for one part of a day's puzzle.
+pub fn answer(part: u8, answer: impl Display) {
+  let recorded = COLLECTOR.with_borrow_mut(|collector| {
+    let Some(collector) = collector else {
+      return false;
+    };
+
+    let now = Instant::now();
+    collector.parts.push(PartResult {
+      part,
+      answer: answer.to_string(),
+      elapsed: now - collector.last_answer_at,
+    });
+    collector.last_answer_at = now;
+    true
+  });
+
+  if !recorded {
+    println!("Part {part}: {answer}");
+  }
+}
+
+pub struct Day {
+  pub day: u8,
+  pub solve: fn(),
+}
+
+#[cfg(feature = "local")]
+pub const DAYS: &[Day] = &[
+  Day {
+    day: 1,
+    solve: crate::day1::solve,
+  },
+  Day {
+    day: 2,
+    solve: crate::day2::solve,
+  },
+  Day {
+    day: 3,
+    solve: crate::day3::solve,
+  },
+  Day {
+    day: 4,
+    solve: crate::day4::solve,
+  },
+  Day {
+    day: 5,
+    solve: crate::day5::solve,
+  },
+  Day {
+    day: 6,
+    solve: crate::day6::solve,
+  },
+  Day {
+    day: 7,
+    solve: crate::day7::solve,
+  },
+  Day {
+    day: 8,
+    solve: crate::day8::solve,
+  },
+  Day {
+    day: 9,
+    solve: crate::day9::solve,
+  },
+  Day {
+    day: 10,
+    solve: crate::day10::solve,
+  },
+  Day {
+    day: 11,
+    solve: crate::day11::solve,
+  },
+  Day {
+    day: 12,
+    solve: crate::day12::solve,
+  },
+  Day {
+    day: 13,
+    solve: crate::day13::solve,
+  },
+  Day {
+    day: 14,
+    solve: crate::day14::solve,
+  },
+  Day {
+    day: 15,
+    solve: crate::day15::solve,
+  },
+  Day {
+    day: 16,
+    solve: crate::day16::solve,
+  },
+  Day {
+    day: 17,
+    solve: crate::day17::solve,
+  },
+  Day {
+    day: 18,
+    solve: crate::day18::solve,
+  },
+];
+
+#[derive(Clone, Debug)]
+pub struct PartResult {
+  pub part: u8,
+  pub answer: String,
+  /// Time since the day started or since the previous part's answer was reported
+  pub elapsed: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct DayResult {
+  pub day: u8,
+  pub parts: Vec<PartResult>,
+  pub elapsed: Duration,
+  /// The panic message if the day panicked.  Any parts answered before the panic are still kept.
+  pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunOptions {
+  /// Run the days concurrently rather than one after another
+  pub parallel: bool,
+  /// Size of the rayon pool used for running the days as well as for any parallelism within them.
+  /// Uses rayon's default if not set.
+  pub threads: Option<usize>,
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+  if let Some(msg) = payload.downcast_ref::<&str>() {
+    msg.to_string()
+  } else if let Some(msg) = payload.downcast_ref::<String>() {
+    msg.clone()
+  } else {
+    "unknown panic".to_owned()
+  }
+}
+
+pub fn run_day(day: &Day) -> DayResult {
+  // rayon may run another day on this thread while this one is blocked on a parallel iterator, so
+  // the previous collector is stashed and restored rather than assuming there isn't one.
+  let prev = COLLECTOR.replace(Some(Collector {
+    last_answer_at: Instant::now(),
+    parts: Vec::new(),
+  }));
+
+  let start = Instant::now();
+  let res = panic::catch_unwind(AssertUnwindSafe(day.solve));
+  let elapsed = start.elapsed();
+
+  let collector = COLLECTOR.replace(prev).unwrap();
+  DayResult {
+    day: day.day,
+    parts: collector.parts,
+    elapsed,
+    error: res.err().map(panic_message),
+  }
+}
+
+/// Runs all of `days`, returning their results in the same order.  A panic in one day is recorded
+/// in its result and doesn't affect any of the others.
+pub fn run_all(days: &[Day], opts: RunOptions) -> Vec<DayResult> {
+  let mut pool = rayon::ThreadPoolBuilder::new();
+  if let Some(threads) = opts.threads {
+    pool = pool.num_threads(threads);
+  }
+  let pool = pool.build().expect("failed to build thread pool");
+
+  let was_quiet = QUIET.swap(true, Ordering::Relaxed);
+  let results = pool.install(|| {
+    if opts.parallel {
+      days.par_iter().map(run_day).collect()
+    } else {
+      days.iter().map(run_day).collect()
+    }
+  });
+  QUIET.store(was_quiet, Ordering::Relaxed);
+  results
+}
+
+fn fmt_duration(d: Duration) -> String {
+  let secs = d.as_secs_f64();
+  if secs >= 1. {
+    format!("{secs:.2}s")
+  } else if secs >= 1e-3 {
+    format!("{:.2}ms", secs * 1e3)
+  } else {
+    format!("{:.2}µs", secs * 1e6)
+  }
+}
+
+/// Prints a table with one row for each part of each day along with totals and failures.  `opts`
+/// should be the options the days were run with, since times mean something different when the
+/// days ran in parallel.
+pub fn print_summary(results: &[DayResult], opts: RunOptions, wall_time: Duration) {
+  let mut rows = Vec::new();
+  for res in results {
+    for part in &res.parts {
+      rows.push((
+        res.day,
+        part.part.to_string(),
+        part.answer.clone(),
+        fmt_duration(part.elapsed),
+      ));
+    }
+    if let Some(err) = &res.error {
+      rows.push((
+        res.day,
+        "-".to_owned(),
+        format!("PANICKED: {err}"),
+        fmt_duration(res.elapsed),
+      ));
+    }
+  }
+
+  let answer_width = rows
+    .iter()
+    .map(|(_, _, answer, _)| answer.chars().count())
+    .max()
+    .unwrap_or(0)
+    .max("answer".len());
+
+  println!("{:>3} | {:>4} | {:<answer_width$} | {:>10}", "day", "part", "answer", "time");
+  println!("{:-<3}-+-{:-<4}-+-{:-<answer_width$}-+-{:-<10}", "", "", "", "");
+  for (day, part, answer, time) in rows {
+    println!("{day:>3} | {part:>4} | {answer:<answer_width$} | {time:>10}");
+  }
+  println!();
+
+  let total_time = results.iter().map(|res| res.elapsed).sum::<Duration>();
+  let failures = results
+    .iter()
+    .filter(|res| res.error.is_some())
+    .map(|res| res.day.to_string())
+    .collect::<Vec<_>>();
+  println!("days run: {}", results.len());
+  if opts.parallel {
+    // days overlapped and competed for the same threads, so their times don't add up to anything
+    // meaningful and only the wall time is comparable to a sequential run
+    println!("day times are wall-clock while running in parallel with the other days");
+    println!("sum of day times: {}", fmt_duration(total_time));
+  } else {
+    println!("total time: {}", fmt_duration(total_time));
+  }
+  println!("wall time: {}", fmt_duration(wall_time));
+  if failures.is_empty() {
+    println!("failures: none");
+  } else {
+    println!("failures: {} (days {})", failures.len(), failures.join(", "));
+  }
+}
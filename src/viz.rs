@@ -0,0 +1,290 @@
+//! Terminal player for visualizing the simulation days.
+//!
+//! Simulations produce an iterator of `Frame`s which get drawn in place in the terminal.  While
+//! playing, the following keys are available:
+//!
+//!  * `space`: pause/resume
+//!  * `n`: step forward one frame (pauses playback)
+//!  * `+`/`-`: double/halve the playback speed
+//!  * `q`: stop playing
+
+use std::{
+  fmt::Write as _,
+  fs::File,
+  io::{self, IsTerminal, Read, Write},
+  process::Command,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, RecvTimeoutError},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+
+use fxhash::FxHashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// A single frame of a simulation: a grid of chars plus a caption shown underneath it.
+#[derive(Clone, Debug, Default)]
+pub struct Frame {
+  pub cells: Vec<Vec<char>>,
+  pub caption: String,
+  /// If set, playback is paused once this frame is shown
+  pub pause: bool,
+}
+
+impl Frame {
+  pub fn new(cells: Vec<Vec<char>>, caption: impl Into<String>) -> Self {
+    Frame {
+      cells,
+      caption: caption.into(),
+      pause: false,
+    }
+  }
+
+  pub fn pause(mut self) -> Self {
+    self.pause = true;
+    self
+  }
+
+  pub fn from_fn(
+    width: usize,
+    height: usize,
+    caption: impl Into<String>,
+    f: impl Fn(usize, usize) -> char,
+  ) -> Self {
+    let cells = (0..height)
+      .map(|y| (0..width).map(|x| f(x, y)).collect())
+      .collect();
+    Self::new(cells, caption)
+  }
+}
+
+enum Control {
+  TogglePause,
+  Step,
+  Faster,
+  Slower,
+  Quit,
+}
+
+impl Control {
+  fn from_key(key: u8) -> Option<Self> {
+    match key {
+      b' ' | b'p' => Some(Control::TogglePause),
+      b'n' | b'.' => Some(Control::Step),
+      b'+' | b'=' => Some(Control::Faster),
+      b'-' | b'_' => Some(Control::Slower),
+      b'q' => Some(Control::Quit),
+      _ => None,
+    }
+  }
+}
+
+/// Puts the terminal into non-canonical mode for as long as it's alive so that keypresses can be
+/// read without waiting for a newline.  Reads also time out after a tenth of a second so that the
+/// thread reading keys can notice when it's time to stop.
+struct RawMode {
+  /// Terminal settings from before raw mode was enabled, as printed by `stty -g`
+  saved: String,
+}
+
+impl RawMode {
+  fn stty(args: &[&str]) -> Option<String> {
+    let tty = File::open("/dev/tty").ok()?;
+    let output = Command::new("stty").args(args).stdin(tty).output().ok()?;
+    output
+      .status
+      .success()
+      .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+  }
+
+  fn enable() -> Option<Self> {
+    let saved = Self::stty(&["-g"])?;
+    Self::stty(&["-icanon", "-echo", "min", "0", "time", "1"])?;
+    Some(RawMode { saved })
+  }
+}
+
+impl Drop for RawMode {
+  fn drop(&mut self) { Self::stty(&[&self.saved]); }
+}
+
+/// Reads keys from stdin on a background thread until stopped.  Stdin is only locked for one read
+/// at a time, and reads time out in raw mode, so stopping never waits on a keypress.
+struct KeyReader {
+  stop: Arc<AtomicBool>,
+  thread: std::thread::JoinHandle<()>,
+}
+
+impl KeyReader {
+  fn spawn(tx: mpsc::Sender<Control>) -> Self {
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = std::thread::spawn({
+      let stop = Arc::clone(&stop);
+      move || {
+        let mut key = [0u8];
+        while !stop.load(Ordering::Relaxed) {
+          match io::stdin().lock().read(&mut key) {
+            // timed out without a keypress
+            Ok(0) => continue,
+            Ok(_) => (),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+          }
+          if let Some(control) = Control::from_key(key[0]) {
+            if tx.send(control).is_err() {
+              break;
+            }
+          }
+        }
+      }
+    });
+    KeyReader { stop, thread }
+  }
+
+  fn stop(self) {
+    self.stop.store(true, Ordering::Relaxed);
+    let _ = self.thread.join();
+  }
+}
+
+const MIN_DELAY: Duration = Duration::from_millis(1);
+const MAX_DELAY: Duration = Duration::from_secs(10);
+
+pub struct Player {
+  delay: Duration,
+  start_paused: bool,
+  palette: FxHashMap<char, Rgb>,
+}
+
+impl Default for Player {
+  fn default() -> Self {
+    Player {
+      delay: Duration::from_millis(100),
+      start_paused: false,
+      palette: FxHashMap::default(),
+    }
+  }
+}
+
+impl Player {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn fps(mut self, fps: f64) -> Self {
+    self.delay = Duration::from_secs_f64(1. / fps).clamp(MIN_DELAY, MAX_DELAY);
+    self
+  }
+
+  pub fn start_paused(mut self, start_paused: bool) -> Self {
+    self.start_paused = start_paused;
+    self
+  }
+
+  /// Draws all cells containing `c` with the provided color.  Cells with no color set use the
+  /// terminal's default foreground color.
+  pub fn color(mut self, c: char, color: Rgb) -> Self {
+    self.palette.insert(c, color);
+    self
+  }
+
+  fn render(&self, frame: &Frame, paused: bool, delay: Duration) -> String {
+    // move to the top left and hide the cursor rather than clearing the screen to avoid flicker
+    let mut out = String::from("\x1b[H\x1b[?25l");
+
+    for row in &frame.cells {
+      let mut cur_color = None;
+      for &c in row {
+        let color = self.palette.get(&c);
+        if color != cur_color {
+          match color {
+            Some(Rgb(r, g, b)) => write!(out, "\x1b[38;2;{r};{g};{b}m").unwrap(),
+            None => out.push_str("\x1b[0m"),
+          }
+          cur_color = color;
+        }
+        out.push(c);
+      }
+      out.push_str("\x1b[0m\x1b[K\n");
+    }
+
+    let state = if paused { "paused" } else { "playing" };
+    let fps = 1. / delay.as_secs_f64();
+    writeln!(out, "\x1b[K{}", frame.caption).unwrap();
+    write!(
+      out,
+      "\x1b[K[{state} @ {fps:.1} fps] space: pause, n: step, +/-: speed, q: quit\x1b[J"
+    )
+    .unwrap();
+
+    out
+  }
+
+  /// Plays `frames` in the terminal, returning once they're exhausted or playback is stopped.
+  pub fn play<I: IntoIterator<Item = Frame>>(&self, frames: I) {
+    let raw_mode = RawMode::enable();
+    let (tx, rx) = mpsc::channel();
+    // without raw mode on a terminal, reads never time out and an empty read means EOF, so there's
+    // no way to read keys that can be stopped.  Dropping `tx` then just plays everything through.
+    let key_reader =
+      (raw_mode.is_some() && io::stdin().is_terminal()).then(|| KeyReader::spawn(tx));
+
+    let mut stdout = std::io::stdout().lock();
+    let _ = write!(stdout, "\x1b[2J");
+
+    let mut delay = self.delay;
+    let mut paused = self.start_paused;
+    'frames: for frame in frames {
+      paused |= frame.pause;
+      let next_frame_at = Instant::now() + delay;
+      loop {
+        let _ = stdout.write_all(self.render(&frame, paused, delay).as_bytes());
+        let _ = stdout.flush();
+
+        let control = if paused {
+          match rx.recv() {
+            Ok(control) => control,
+            // no way to ever get unpaused, so just play everything through
+            Err(_) => {
+              paused = false;
+              continue;
+            },
+          }
+        } else {
+          match rx.recv_timeout(next_frame_at.saturating_duration_since(Instant::now())) {
+            Ok(control) => control,
+            Err(RecvTimeoutError::Timeout) => break,
+            Err(RecvTimeoutError::Disconnected) => {
+              std::thread::sleep(next_frame_at.saturating_duration_since(Instant::now()));
+              break;
+            },
+          }
+        };
+
+        match control {
+          Control::TogglePause => {
+            paused = !paused;
+            if !paused {
+              break;
+            }
+          },
+          Control::Step => {
+            paused = true;
+            break;
+          },
+          Control::Faster => delay = (delay / 2).max(MIN_DELAY),
+          Control::Slower => delay = (delay * 2).min(MAX_DELAY),
+          Control::Quit => break 'frames,
+        }
+      }
+    }
+
+    let _ = writeln!(stdout, "\x1b[?25h");
+    if let Some(key_reader) = key_reader {
+      key_reader.stop();
+    }
+    drop(raw_mode);
+  }
+}